@@ -0,0 +1,430 @@
+// Command line options. Named flags are preferred, but the two original
+// positionals (save path, output path) are still accepted so existing
+// scripts keep working.
+
+use std::path::Path;
+
+use hoi4_parser::{DEFAULT_ACTIVITY_EPSILON, DEFAULT_FOCUS_DAYS};
+
+pub const DEFAULT_INPUT: &str = "autosave.hoi4";
+pub const DEFAULT_OUTPUT: &str = "../data/game_data.json";
+// The file --output-dir writes into its directory
+pub const OUTPUT_FILE_NAME: &str = "game_data.json";
+// Where --batch writes its files when no --output is given
+pub const DEFAULT_BATCH_OUTPUT: &str = "../data";
+
+pub const HELP: &str = "\
+Usage: hoi4_parser [OPTIONS] [SAVE_PATH] [OUTPUT_PATH]
+
+Arguments:
+  [SAVE_PATH]              HOI4 save file to parse, or - for stdin (same as --input)
+  [OUTPUT_PATH]            Where to write the extracted JSON, or - for stdout (same as --output)
+
+Options:
+  -i, --input <PATH>       HOI4 save file to parse, or - for stdin [default: autosave.hoi4]
+  -o, --output <PATH>      Where to write the extracted JSON, or - for stdout [default: ../data/game_data.json]
+      --output-dir <DIR>   Write game_data.json into DIR, creating it if missing (with --batch,
+                           the directory for the per-save files)
+      --split-countries <DIR>
+                           Also write each country's data to DIR/<TAG>.json, creating DIR
+                           if missing
+      --batch <DIR>        Parse every *.hoi4 (or *.hoi4.gz) save in DIR, writing
+                           game_data_<save>.json files to the --output directory [default: ../data]
+      --diff <OLD> <NEW>   Parse two saves and emit what changed between them
+                           (to stdout unless --output is given)
+      --tokens <PATH>      Token definitions file (0xXXXX value lines, or the binary .bin form),
+                           required for binary saves
+      --compact            Write single-line JSON instead of pretty-printed output
+      --ndjson             Write one JSON object per line: metadata, each country, each war,
+                           each faction, then the other sections
+  -q, --quiet              Silence progress messages (they go to stderr; the summary is kept)
+      --header-only        Write just the metadata (player, date, version, ...), skipping
+                           the per-country extraction
+      --stats              Print wars, factions, completed focuses and the most common ruling
+                           ideology after the summary
+      --include-inactive   Emit every country, skipping the active-country filter
+      --min-political-power <PP>
+                           Also treat countries with at least this much political power as active
+      --activity-epsilon <E>
+                           How far stability or war support must be from 0.5 for a country
+                           to count as active [default: 0.000001]
+      --event-counts       Emit events as an {event: times_fired} map instead of a list
+      --events-flat        Emit events as a plain list of names, without tag or date
+      --event-ignore <TOKENS>
+                           Comma-separated event names to drop as noise, on top of id, =,
+                           bare numbers and single characters
+      --events-since <DAYS>
+                           Drop events fired more than DAYS in-game days before the save
+                           date (events the save doesn't date are kept)
+      --focus-days <DAYS>  Length of a national focus, for days_remaining [default: 70]
+      --include-states     Also emit the owner, controller and building levels of every
+                           owned state
+      --all-decisions      Also list decisions that have no running timer
+      --equipment-detail   Report stockpiled equipment by type (infantry_equipment_1) instead
+                           of totals by archetype (infantry_equipment)
+      --ai-strategy        Also emit each country's AI strategy entries (what the game's AI
+                           is planning)
+      --max-countries <N>  Keep at most N countries: the player, then those with the most
+                           political power (applied after the active filter and --only)
+      --localization <DIR> HOI4 localisation folder (e.g. localisation/english) used to
+                           name focuses, ideas, events and technologies
+      --power-ranking      Also emit the countries ranked by factories, divisions and
+                           research slots
+      --only <TAGS>        Comma-separated country tags to keep, e.g. GER,SOV,USA (alias: --countries)
+      --fields <NAMES>     Comma-separated country fields to keep, e.g. stability,war_support,focus
+      --include-null-fields
+                           Write every country field, as null when a country has no value,
+                           so all countries have the same keys
+      --timeout <SECS>     Give up with an error if extraction takes longer than this
+      --schema             Print a JSON Schema describing the output and exit
+      --validate           Check the output against that schema before writing it, failing
+                           if it doesn't match
+  -V, --version            Print the parser version and exit
+  -h, --help               Print this help and exit";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Args {
+    pub input: String,
+    pub output: String,
+    pub output_dir: Option<String>,
+    pub split_countries: Option<String>,
+    pub batch: Option<String>,
+    pub diff: Option<(String, String)>,
+    pub tokens: Option<String>,
+    pub compact: bool,
+    pub ndjson: bool,
+    pub quiet: bool,
+    pub stats: bool,
+    pub header_only: bool,
+    pub only: Option<Vec<String>>,
+    pub fields: Option<Vec<String>>,
+    pub include_null_fields: bool,
+    pub include_inactive: bool,
+    pub min_political_power: Option<f64>,
+    pub activity_epsilon: f64,
+    pub focus_days: f64,
+    pub event_counts: bool,
+    pub events_flat: bool,
+    pub event_ignore: Vec<String>,
+    pub events_since: Option<u32>,
+    pub include_states: bool,
+    pub all_decisions: bool,
+    pub equipment_detail: bool,
+    pub ai_strategy: bool,
+    pub max_countries: Option<usize>,
+    pub localization: Option<String>,
+    pub power_ranking: bool,
+    pub timeout: Option<f64>,
+    pub schema: bool,
+    pub validate: bool,
+    pub version: bool,
+    pub help: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Args {
+            input: DEFAULT_INPUT.to_string(),
+            output: DEFAULT_OUTPUT.to_string(),
+            output_dir: None,
+            split_countries: None,
+            batch: None,
+            diff: None,
+            tokens: None,
+            compact: false,
+            ndjson: false,
+            quiet: false,
+            stats: false,
+            header_only: false,
+            only: None,
+            fields: None,
+            include_null_fields: false,
+            include_inactive: false,
+            min_political_power: None,
+            activity_epsilon: DEFAULT_ACTIVITY_EPSILON,
+            focus_days: DEFAULT_FOCUS_DAYS,
+            event_counts: false,
+            events_flat: false,
+            event_ignore: Vec::new(),
+            events_since: None,
+            include_states: false,
+            all_decisions: false,
+            equipment_detail: false,
+            ai_strategy: false,
+            max_countries: None,
+            localization: None,
+            power_ranking: false,
+            timeout: None,
+            schema: false,
+            validate: false,
+            version: false,
+            help: false,
+        }
+    }
+}
+
+impl Args {
+    pub fn parse() -> Result<Args, String> {
+        Args::parse_from(std::env::args().skip(1))
+    }
+
+    // Parse an argv vector, excluding the program name
+    pub fn parse_from<I>(argv: I) -> Result<Args, String>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut args = Args::default();
+        let mut input = None;
+        let mut output = None;
+        let mut positionals = Vec::new();
+
+        let mut argv = argv.into_iter();
+        while let Some(arg) = argv.next() {
+            // Support both "--flag value" and "--flag=value"
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+                _ => (arg.clone(), None),
+            };
+            let mut took_value = false;
+            let mut value = |name: &str| -> Result<String, String> {
+                took_value = true;
+                inline_value
+                    .clone()
+                    .or_else(|| argv.next())
+                    .ok_or_else(|| format!("{} requires a value", name))
+            };
+
+            match flag.as_str() {
+                "-i" | "--input" => input = Some(value("--input")?),
+                "-o" | "--output" => output = Some(value("--output")?),
+                "--output-dir" => args.output_dir = Some(value("--output-dir")?),
+                "--split-countries" => args.split_countries = Some(value("--split-countries")?),
+                "--batch" => args.batch = Some(value("--batch")?),
+                "--diff" => {
+                    let old = value("--diff")?;
+                    let new = argv.next().ok_or("--diff requires two save paths")?;
+                    args.diff = Some((old, new));
+                }
+                "--tokens" => args.tokens = Some(value("--tokens")?),
+                "--localization" => args.localization = Some(value("--localization")?),
+                "--compact" => args.compact = true,
+                "--ndjson" => args.ndjson = true,
+                "-q" | "--quiet" => args.quiet = true,
+                "--stats" => args.stats = true,
+                "--header-only" => args.header_only = true,
+                "--only" | "--countries" => args.only = Some(parse_tag_list(&value("--only")?)?),
+                "--fields" => args.fields = Some(parse_field_list(&value("--fields")?)?),
+                "--include-null-fields" => args.include_null_fields = true,
+                "--include-inactive" => args.include_inactive = true,
+                "--min-political-power" => {
+                    let pp = value("--min-political-power")?;
+                    args.min_political_power = Some(
+                        pp.parse().map_err(|_| format!("--min-political-power expects a number, got '{}'", pp))?,
+                    );
+                }
+                "--activity-epsilon" => {
+                    let epsilon = value("--activity-epsilon")?;
+                    match epsilon.parse::<f64>() {
+                        Ok(epsilon) if epsilon.is_finite() && epsilon >= 0.0 => args.activity_epsilon = epsilon,
+                        _ => return Err(format!("--activity-epsilon expects a non-negative number, got '{}'", epsilon)),
+                    }
+                }
+                "--focus-days" => {
+                    let days = value("--focus-days")?;
+                    args.focus_days = match days.parse::<f64>() {
+                        Ok(days) if days > 0.0 => days,
+                        _ => return Err(format!("--focus-days expects a positive number, got '{}'", days)),
+                    };
+                }
+                "--event-counts" => args.event_counts = true,
+                "--events-flat" => args.events_flat = true,
+                "--event-ignore" => {
+                    let tokens = value("--event-ignore")?;
+                    args.event_ignore.extend(
+                        tokens.split(',').map(str::trim).filter(|token| !token.is_empty()).map(str::to_string),
+                    );
+                }
+                "--events-since" => {
+                    let days = value("--events-since")?;
+                    args.events_since = Some(
+                        days.parse().map_err(|_| format!("--events-since expects a whole number of days, got '{}'", days))?,
+                    );
+                }
+                "--include-states" => args.include_states = true,
+                "--all-decisions" => args.all_decisions = true,
+                "--equipment-detail" => args.equipment_detail = true,
+                "--ai-strategy" => args.ai_strategy = true,
+                "--power-ranking" => args.power_ranking = true,
+                "--max-countries" => {
+                    let max = value("--max-countries")?;
+                    args.max_countries = Some(
+                        max.parse().map_err(|_| format!("--max-countries expects a whole number, got '{}'", max))?,
+                    );
+                }
+                "--timeout" => {
+                    let secs = value("--timeout")?;
+                    match secs.parse::<f64>() {
+                        Ok(secs) if secs.is_finite() && secs > 0.0 => args.timeout = Some(secs),
+                        _ => return Err(format!("--timeout expects a positive number of seconds, got '{}'", secs)),
+                    }
+                }
+                "--schema" => args.schema = true,
+                "--validate" => args.validate = true,
+                "-V" | "--version" => args.version = true,
+                "-h" | "--help" => args.help = true,
+                _ if flag.starts_with('-') && flag.len() > 1 => {
+                    return Err(format!("unknown option '{}'", flag));
+                }
+                _ => positionals.push(arg),
+            }
+            // A switch given "--flag=value" would otherwise be silently enabled, whatever the value
+            if inline_value.is_some() && !took_value {
+                return Err(format!("{} doesn't take a value", flag));
+            }
+        }
+
+        if positionals.len() > 2 {
+            return Err(format!("unexpected argument '{}'", positionals[2]));
+        }
+        let mut positionals = positionals.into_iter();
+
+        // Named flags win over positionals when both are given
+        if let Some(path) = input.or_else(|| positionals.next()) {
+            args.input = path;
+        }
+        let output = output.or_else(|| positionals.next());
+        if args.output_dir.is_some() {
+            if output.is_some() {
+                return Err("--output-dir can't be combined with an output path".to_string());
+            }
+            if args.diff.is_some() {
+                return Err("--output-dir doesn't apply to --diff; use --output".to_string());
+            }
+        }
+        for (set, flag) in [
+            (args.split_countries.is_some(), "--split-countries"),
+            (args.ndjson, "--ndjson"),
+            (args.header_only, "--header-only"),
+        ] {
+            if set && (args.batch.is_some() || args.diff.is_some()) {
+                return Err(format!("{} only applies to a single save", flag));
+            }
+        }
+        if args.event_counts && args.events_flat {
+            return Err("--event-counts and --events-flat can't be combined".to_string());
+        }
+        if args.header_only && (args.ndjson || args.split_countries.is_some()) {
+            return Err("--header-only can't be combined with --ndjson or --split-countries".to_string());
+        }
+        if let Some(path) = output {
+            args.output = path;
+        } else if let Some(dir) = &args.output_dir {
+            args.output = if args.batch.is_some() {
+                dir.clone()
+            } else {
+                Path::new(dir).join(OUTPUT_FILE_NAME).display().to_string()
+            };
+        } else if args.batch.is_some() {
+            args.output = DEFAULT_BATCH_OUTPUT.to_string();
+        } else if args.diff.is_some() {
+            args.output = "-".to_string();
+        }
+
+        Ok(args)
+    }
+}
+
+// Parse a comma-separated list of country tags (GER, or dynamic ones like D01)
+fn parse_tag_list(list: &str) -> Result<Vec<String>, String> {
+    let mut tags = Vec::new();
+    for tag in list.split(',').map(str::trim).filter(|tag| !tag.is_empty()) {
+        if !hoi4_parser::extraction::is_country_tag(tag) {
+            return Err(format!(
+                "'{}' is not a valid country tag (expected an uppercase letter then two uppercase letters or digits)",
+                tag
+            ));
+        }
+        tags.push(tag.to_string());
+    }
+    if tags.is_empty() {
+        return Err("--only requires at least one country tag".to_string());
+    }
+    Ok(tags)
+}
+
+// Parse a comma-separated list of country field names. Whether they exist
+// is only known once there's output to check them against.
+fn parse_field_list(list: &str) -> Result<Vec<String>, String> {
+    let fields: Vec<String> = list
+        .split(',')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .map(str::to_string)
+        .collect();
+    if fields.is_empty() {
+        return Err("--fields requires at least one field name".to_string());
+    }
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(argv: &[&str]) -> Result<Args, String> {
+        Args::parse_from(argv.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn no_arguments_keep_the_old_defaults() {
+        assert_eq!(parse(&[]).unwrap(), Args::default());
+        assert_eq!(Args::default().input, "autosave.hoi4");
+        assert_eq!(Args::default().output, "../data/game_data.json");
+    }
+
+    #[test]
+    fn representative_argv() {
+        let args = parse(&["save.hoi4", "--output=out.json", "--compact", "--only", "GER,D01", "--focus-days", "35"]).unwrap();
+        let expected = Args {
+            input: "save.hoi4".to_string(),
+            output: "out.json".to_string(),
+            compact: true,
+            only: Some(vec!["GER".to_string(), "D01".to_string()]),
+            focus_days: 35.0,
+            ..Args::default()
+        };
+        assert_eq!(args, expected);
+
+        // The two positionals still work, and named flags win over them
+        let args = parse(&["in.hoi4", "out.json"]).unwrap();
+        assert_eq!((args.input.as_str(), args.output.as_str()), ("in.hoi4", "out.json"));
+        let args = parse(&["in.hoi4", "-i", "named.hoi4"]).unwrap();
+        assert_eq!(args.input, "named.hoi4");
+    }
+
+    #[test]
+    fn unknown_flags_and_missing_values_are_errors() {
+        assert_eq!(parse(&["--no-such-flag"]).unwrap_err(), "unknown option '--no-such-flag'");
+        assert_eq!(parse(&["--output"]).unwrap_err(), "--output requires a value");
+        assert_eq!(parse(&["--diff", "old.hoi4"]).unwrap_err(), "--diff requires two save paths");
+        assert!(parse(&["--max-countries", "many"]).is_err());
+        assert!(parse(&["a", "b", "c"]).unwrap_err().contains("unexpected argument 'c'"));
+    }
+
+    #[test]
+    fn switches_reject_inline_values() {
+        assert_eq!(parse(&["--compact=no"]).unwrap_err(), "--compact doesn't take a value");
+        assert_eq!(parse(&["--quiet=false"]).unwrap_err(), "--quiet doesn't take a value");
+        assert_eq!(parse(&["--stats=0"]).unwrap_err(), "--stats doesn't take a value");
+        assert!(parse(&["--compact", "--stats"]).unwrap().stats);
+    }
+
+    #[test]
+    fn conflicting_flags_are_errors() {
+        assert!(parse(&["--event-counts", "--events-flat"]).is_err());
+        assert!(parse(&["--header-only", "--ndjson"]).is_err());
+        assert!(parse(&["--header-only", "--split-countries", "out"]).is_err());
+        assert!(parse(&["--batch", "saves", "--ndjson"]).is_err());
+        assert!(parse(&["--diff", "old.hoi4", "new.hoi4", "--header-only"]).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+// What changed between two parsed saves, for "what happened since the last
+// autosave". Works on the structured output rather than the raw save text,
+// so it sees exactly what a consumer of game_data.json would.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use serde::Serialize;
+
+use crate::extraction::War;
+use crate::{CountryData, GameData};
+
+// Party popularity moves (in percentage points) smaller than this are noise
+pub const IDEOLOGY_SHIFT_THRESHOLD: f64 = 1.0;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SaveDiff {
+    pub from_date: String,
+    pub to_date: String,
+    pub focuses_completed: BTreeMap<String, Vec<String>>,
+    pub wars_started: Vec<War>,
+    pub wars_ended: Vec<War>,
+    pub ruling_party_changes: Vec<RulingPartyChange>,
+    pub ideology_shifts: Vec<IdeologyShift>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RulingPartyChange {
+    pub tag: String,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IdeologyShift {
+    pub tag: String,
+    pub ideology: String,
+    pub from: f64,
+    pub to: f64,
+}
+
+pub fn diff_saves(old: &GameData, new: &GameData) -> SaveDiff {
+    let old_countries: HashMap<&str, &CountryData> =
+        old.countries.iter().map(|country| (country.tag.as_str(), country)).collect();
+
+    let mut diff = SaveDiff {
+        from_date: old.metadata.date.clone(),
+        to_date: new.metadata.date.clone(),
+        focuses_completed: BTreeMap::new(),
+        wars_started: new.wars.iter().filter(|war| !old.wars.contains(war)).cloned().collect(),
+        wars_ended: old.wars.iter().filter(|war| !new.wars.contains(war)).cloned().collect(),
+        ruling_party_changes: Vec::new(),
+        ideology_shifts: Vec::new(),
+    };
+
+    // Countries only present in one save (filtered out, or not yet
+    // instantiated) have nothing to compare against
+    let mut countries: Vec<&CountryData> = new.countries.iter().collect();
+    countries.sort_by(|a, b| a.tag.cmp(&b.tag));
+    for country in countries {
+        let Some(previous) = old_countries.get(country.tag.as_str()) else {
+            continue;
+        };
+
+        let before = completed_focuses(previous);
+        let newly_completed: Vec<String> = completed_focuses(country)
+            .into_iter()
+            .filter(|focus| !before.contains(focus))
+            .map(str::to_string)
+            .collect();
+        if !newly_completed.is_empty() {
+            diff.focuses_completed.insert(country.tag.clone(), newly_completed);
+        }
+
+        let ruling_party = |country: &CountryData| {
+            country.data["politics"]["ruling_party"].as_str().map(str::to_string)
+        };
+        let (from, to) = (ruling_party(previous), ruling_party(country));
+        if from != to {
+            diff.ruling_party_changes.push(RulingPartyChange { tag: country.tag.clone(), from, to });
+        }
+
+        // A party missing from either save counts as 0%, so parties that
+        // vanished show up as well as ones that appeared
+        let (before, after) = (popularities(previous), popularities(country));
+        let ideologies: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+        for ideology in ideologies {
+            let from = before.get(ideology).copied().unwrap_or(0.0);
+            let to = after.get(ideology).copied().unwrap_or(0.0);
+            if (to - from).abs() >= IDEOLOGY_SHIFT_THRESHOLD {
+                diff.ideology_shifts.push(IdeologyShift { tag: country.tag.clone(), ideology: ideology.clone(), from, to });
+            }
+        }
+    }
+
+    diff
+}
+
+fn completed_focuses(country: &CountryData) -> Vec<&str> {
+    country.data["focus"]["completed"]
+        .as_array()
+        .map(|completed| {
+            // Plain ids, or `{id, name}` pairs when localisation was loaded
+            completed
+                .iter()
+                .filter_map(|focus| focus.as_str().or_else(|| focus["id"].as_str()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn popularities(country: &CountryData) -> BTreeMap<String, f64> {
+    let Some(parties) = country.data["politics"]["parties"].as_object() else {
+        return BTreeMap::new();
+    };
+    parties
+        .iter()
+        .filter_map(|(ideology, party)| Some((ideology.clone(), party["popularity"].as_f64()?)))
+        .collect()
+}
@@ -1,27 +1,74 @@
-use hoi4save::{CountryTag, Hoi4Date};
+use hoi4save::Hoi4Date;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::hash::Hash;
+use std::marker::PhantomData;
 
-// We need to implement our own deserialize_vec_pair since the internal one is private
+// We need to implement our own deserialize_vec_pair since the internal one is
+// private. Entries come back in save order with duplicate keys kept, so the
+// caller can report them instead of one silently replacing the other.
 fn deserialize_vec_pair<'de, D, K, V>(deserializer: D) -> Result<Vec<(K, V)>, D::Error>
 where
     D: serde::Deserializer<'de>,
-    K: Deserialize<'de> + Eq + Hash,
+    K: Deserialize<'de>,
     V: Deserialize<'de>,
 {
-    let map: HashMap<K, V> = HashMap::deserialize(deserializer)?;
-    Ok(map.into_iter().collect())
+    struct VecPairVisitor<K, V>(PhantomData<(K, V)>);
+
+    impl<'de, K: Deserialize<'de>, V: Deserialize<'de>> serde::de::Visitor<'de> for VecPairVisitor<K, V> {
+        type Value = Vec<(K, V)>;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("a map")
+        }
+
+        fn visit_map<A>(self, mut map: A) -> Result<Vec<(K, V)>, A::Error>
+        where
+            A: serde::de::MapAccess<'de>,
+        {
+            let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+            while let Some(entry) = map.next_entry()? {
+                entries.push(entry);
+            }
+            Ok(entries)
+        }
+    }
+
+    deserializer.deserialize_map(VecPairVisitor(PhantomData))
 }
 
 #[derive(Deserialize, Debug, Clone, Serialize)]
 pub struct EnhancedHoi4Save {
     pub player: String,
     pub date: Hoi4Date,
+    #[serde(default)]
+    pub start_date: Option<Hoi4Date>,
+    // Plain strings rather than CountryTag, so an odd modded tag is reported
+    // instead of failing the whole parse
     #[serde(default, deserialize_with = "deserialize_vec_pair")]
-    pub countries: Vec<(CountryTag, EnhancedCountry)>,
+    pub countries: Vec<(String, EnhancedCountry)>,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub checksum: Option<String>,
+    #[serde(default)]
+    pub ironman: Option<bool>,
+    #[serde(default)]
+    pub gameplaysettings: Option<GameplaySettings>,
+}
+
+impl EnhancedHoi4Save {
+    // Text saves record ironman as a 0/1 game setting; a top-level flag, when
+    // present, takes precedence
+    pub fn is_ironman(&self) -> Option<bool> {
+        self.ironman
+            .or_else(|| self.gameplaysettings.as_ref()?.ironman.map(|ironman| ironman != 0))
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Serialize)]
+pub struct GameplaySettings {
     #[serde(default)]
-    pub fired_event_names: Vec<String>,
+    pub ironman: Option<i32>,
 }
 
 #[derive(Deserialize, Debug, Clone, Serialize)]
@@ -35,6 +82,13 @@ pub struct DatabaseCharacter {
     pub name: Option<String>,
     #[serde(default)]
     pub country: Option<String>,
+    #[serde(default)]
+    pub country_leader: bool,
+    #[serde(default)]
+    pub traits: Vec<String>,
+    // Just the traits of the country leader role
+    #[serde(default)]
+    pub leader_traits: Vec<String>,
 }
 
 #[derive(Deserialize, Debug, Clone, Serialize)]
@@ -45,9 +99,10 @@ pub struct CharacterId {
 
 #[derive(Deserialize, Debug, Clone, Serialize)]
 pub struct EnhancedCountry {
-    #[serde(default)]
+    // The save leaves these out when they sit at the game's default
+    #[serde(default = "default_stat")]
     pub stability: f64,
-    #[serde(default)]
+    #[serde(default = "default_stat")]
     pub war_support: f64,
     #[serde(default)]
     pub variables: HashMap<String, f64>,
@@ -55,46 +110,542 @@ pub struct EnhancedCountry {
     pub politics: Option<Politics>,
     #[serde(default)]
     pub focus: Option<Focus>,
+    // Sits next to the focus block in the save; written out inside `focus`
+    #[serde(default, skip_serializing)]
+    pub focus_tree: Option<String>,
     #[serde(default)]
     pub major: Option<bool>,
+    // State id of the capital
+    #[serde(default)]
+    pub capital: Option<u32>,
+    // Set when the country displays under another name and flag (a formable
+    // or puppet variant); the save writes "" otherwise
+    #[serde(default, deserialize_with = "deserialize_non_empty", skip_serializing_if = "Option::is_none")]
+    pub cosmetic_tag: Option<String>,
+    #[serde(default)]
+    pub army_experience: Option<f64>,
+    #[serde(default)]
+    pub navy_experience: Option<f64>,
+    #[serde(default)]
+    pub air_experience: Option<f64>,
+    #[serde(default)]
+    pub command_power: Option<f64>,
+    // The save calls it research_slot, but it's the number of slots
+    #[serde(default, alias = "research_slot")]
+    pub research_slots: Option<u32>,
+    // Filled from the units block by the regex extraction pass
+    #[serde(default, skip_deserializing)]
+    pub divisions: Option<u32>,
+    #[serde(default, skip_deserializing)]
+    pub army_manpower: Option<u64>,
+    #[serde(default, skip_deserializing)]
+    pub ships: Option<u32>,
+    // Total aircraft across the country's air wings
+    #[serde(default, skip_deserializing)]
+    pub planes: Option<u32>,
+    // The save's own resources block is nested, so this is filled by extraction instead
+    #[serde(default, skip_deserializing)]
+    pub resources: HashMap<String, f64>,
+    // Stockpiled equipment, summed by archetype (infantry_equipment), or by
+    // individual type (infantry_equipment_1) with equipment_detail
+    #[serde(default, skip_deserializing)]
+    pub equipment: HashMap<String, f64>,
+    #[serde(default, skip_deserializing)]
+    pub technology: Option<Technology>,
+    // Summed from the states the country owns
+    #[serde(default, skip_deserializing)]
+    pub industry: Option<Industry>,
+    // Filled by the logistics extraction pass, since the save nests these
+    #[serde(default, skip_deserializing)]
+    pub manpower: Option<u64>,
+    #[serde(default, skip_deserializing)]
+    pub fuel: Option<f64>,
+    #[serde(default, skip_deserializing)]
+    pub convoys: Option<u32>,
+    #[serde(default, skip_deserializing)]
+    pub decisions: Vec<ActiveDecision>,
+    // The military advisor slots (chiefs of staff and high command), split
+    // out of the hired advisors
+    #[serde(default, skip_deserializing)]
+    pub high_command: Vec<Advisor>,
+    // Strategic region ids where the country's wings on mission out-fight
+    // everyone else's, from the strategic_air block
+    #[serde(default, skip_deserializing)]
+    pub air_superiority_regions: Vec<String>,
+    // Id of the tech sharing group the country is in, from the top-level
+    // tech_sharing_group blocks
+    #[serde(default, skip_deserializing)]
+    pub research_sharing_group: Option<String>,
+    // Puppet relationships, from the puppet blocks in the diplomacy section:
+    // a subject names its overlord and an overlord lists its subjects
+    #[serde(default, skip_deserializing)]
+    pub overlord: Option<String>,
+    #[serde(default, skip_deserializing)]
+    pub subjects: Vec<String>,
+    // The law idea in force in each vanilla law slot, picked out of
+    // politics.ideas
+    #[serde(default, skip_deserializing)]
+    pub conscription_law: Option<String>,
+    #[serde(default, skip_deserializing)]
+    pub economy_law: Option<String>,
+    #[serde(default, skip_deserializing)]
+    pub trade_law: Option<String>,
+    // Divisions lent to other countries (volunteers, expeditionary forces),
+    // from the units block by the regex extraction pass
+    #[serde(default, skip_deserializing)]
+    pub expeditionary: Vec<Expeditionary>,
+    // Derived from the active wars: everyone on the other side of any war
+    // this country is in
+    #[serde(default, skip_deserializing)]
+    pub at_war: bool,
+    #[serde(default, skip_deserializing)]
+    pub enemies: Vec<String>,
+    // The AI's current plans, only present with ParseOptions::ai_strategy
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub ai_strategies: Option<Vec<AiStrategy>>,
+    // Derived from the state list, so only present when states were extracted
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub controlled_foreign_states: Option<u32>,
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub occupied_home_states: Option<u32>,
+}
+
+// Stability and war support a country starts at, and reports when the save
+// doesn't list them
+pub const DEFAULT_STAT: f64 = 0.5;
+
+// Advisor slots that count towards high_command rather than politics
+pub const HIGH_COMMAND_SLOTS: &[&str] = &["army_chief", "navy_chief", "air_chief", "high_command"];
+
+fn default_stat() -> f64 {
+    DEFAULT_STAT
+}
+
+fn deserialize_non_empty<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value: Option<String> = Option::deserialize(deserializer)?;
+    Ok(value.filter(|value| !value.is_empty()))
+}
+
+#[derive(Deserialize, Debug, Clone, Serialize, Default)]
+pub struct Industry {
+    pub civilian_factories: u32,
+    pub military_factories: u32,
+    pub dockyards: u32,
+}
+
+#[derive(Deserialize, Debug, Clone, Serialize, Default)]
+pub struct Technology {
+    #[serde(default)]
+    pub completed: Vec<String>,
+    #[serde(default)]
+    pub in_progress: Vec<ResearchSlot>,
 }
 
 #[derive(Deserialize, Debug, Clone, Serialize)]
+pub struct ResearchSlot {
+    pub tech: String,
+    pub progress: f64,
+}
+
+#[derive(Deserialize, Debug, Clone, Serialize, PartialEq)]
+pub struct ActiveDecision {
+    pub id: String,
+    pub days_remaining: Option<f64>,
+}
+
+// One of the in-game AI's plans, `ai_strategy={ type=10 id=2 target=55 value=1 }`.
+// The type is the game's numeric strategy kind; `id` is what it is about
+// (a country index, a unit role, ...) and `target` a second party, if any.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct AiStrategy {
+    pub r#type: String,
+    pub id: Option<String>,
+    pub target: Option<String>,
+    pub value: Option<i64>,
+}
+
+#[derive(Deserialize, Debug, Clone, Serialize, PartialEq)]
+pub struct Expeditionary {
+    // The country the divisions serve
+    pub to: String,
+    pub divisions: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct Focus {
-    #[serde(default)]
     pub progress: Option<f64>,
-    #[serde(default)]
+    // The first of current_focuses, for consumers that expect one
     pub current: Option<String>,
-    #[serde(default)]
+    // Every focus in progress. Shared and modded trees can run several at
+    // once, each written as its own `current=` entry.
+    pub current_focuses: Vec<String>,
+    // Raw yes/no flag from the save. A paused focus keeps its `current` id and
+    // progress and resumes from there, so `current` being set does not mean
+    // the focus is actively progressing.
     pub paused: Option<String>,
 }
 
+// Hand-written because the derive rejects the repeated `current` key, which
+// would fail the whole save
+impl<'de> Deserialize<'de> for Focus {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct FocusVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for FocusVisitor {
+            type Value = Focus;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a focus block")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Focus, A::Error>
+            where
+                A: serde::de::MapAccess<'de>,
+            {
+                let mut progress = None;
+                let mut current_focuses = Vec::new();
+                let mut paused = None;
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "progress" => progress = Some(map.next_value::<f64>()?),
+                        "current" => {
+                            if let Some(id) = map.next_value_seed(FocusIdSeed)? {
+                                if !current_focuses.contains(&id) {
+                                    current_focuses.push(id);
+                                }
+                            }
+                        }
+                        "paused" => paused = Some(map.next_value::<String>()?),
+                        _ => {
+                            map.next_value::<serde::de::IgnoredAny>()?;
+                        }
+                    }
+                }
+                Ok(Focus {
+                    progress,
+                    current: current_focuses.first().cloned(),
+                    current_focuses,
+                    paused,
+                })
+            }
+        }
+
+        deserializer.deserialize_map(FocusVisitor)
+    }
+}
+
+// The focus id is normally a quoted string, but an unquoted token or a
+// number reads back as a scalar of another type, and a reference block
+// (`current={ id=... }`) as a block. Scalars are normalised to their text and
+// a block to its `id`, so an odd form never fails the whole save.
+struct FocusIdVisitor;
+
+impl<'de> serde::de::Visitor<'de> for FocusIdVisitor {
+    type Value = Option<String>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a focus id")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(Some(value.to_string()).filter(|value| !value.is_empty()))
+    }
+
+    fn visit_bool<E: serde::de::Error>(self, value: bool) -> Result<Self::Value, E> {
+        Ok(Some(value.to_string()))
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<Self::Value, E> {
+        Ok(Some(value.to_string()))
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(Some(value.to_string()))
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, value: f64) -> Result<Self::Value, E> {
+        Ok(Some(value.to_string()))
+    }
+
+    fn visit_unit<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        let mut id = None;
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "id" && id.is_none() {
+                id = map.next_value_seed(FocusIdSeed)?;
+            } else {
+                map.next_value::<serde::de::IgnoredAny>()?;
+            }
+        }
+        Ok(id)
+    }
+
+    // The streaming reader hands a block over as its flat tokens
+    // (`id`, `=`, `12`, ...) rather than as a map
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        let mut tokens = Vec::new();
+        while let Some(token) = seq.next_element_seed(FocusIdSeed)? {
+            tokens.push(token);
+        }
+        let id = tokens.windows(3).find_map(|window| match window {
+            [Some(key), Some(op), value] if key == "id" && op == "=" => Some(value.clone()),
+            _ => None,
+        });
+        Ok(id.flatten())
+    }
+}
+
+// Reads a focus id in any of the forms FocusIdVisitor accepts
+struct FocusIdSeed;
+
+impl<'de> serde::de::DeserializeSeed<'de> for FocusIdSeed {
+    type Value = Option<String>;
+
+    fn deserialize<D: serde::Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(FocusIdVisitor)
+    }
+}
+
+impl Focus {
+    pub fn is_paused(&self) -> bool {
+        self.paused.as_deref() == Some("yes")
+    }
+
+    // `progress` counts days spent on the current focus, so the remaining
+    // time is the focus length minus that. Vanilla focuses are 10 steps of
+    // 7 days (70 days); modded trees can pass a different length.
+    pub fn days_remaining(&self, focus_days: f64) -> Option<f64> {
+        if self.current.is_none() || !focus_days.is_finite() || focus_days <= 0.0 {
+            return None;
+        }
+        let progress = self.progress.unwrap_or(0.0).max(0.0);
+        Some((focus_days - progress).max(0.0))
+    }
+}
+
 #[derive(Deserialize, Debug, Clone, Serialize)]
 pub struct Politics {
     #[serde(default)]
     pub ruling_party: Option<String>,
     #[serde(default)]
     pub political_power: Option<f64>,
+    // Vanilla saves don't store the daily gain (the game recomputes it from
+    // modifiers every day), so this is None unless the save carries an
+    // explicit `political_power_daily`
     #[serde(default)]
-    pub parties: Option<Parties>,
+    pub political_power_daily: Option<f64>,
     #[serde(default)]
-    pub ideas: Option<Vec<String>>,
+    pub parties: Option<Parties>,
+    #[serde(default, deserialize_with = "deserialize_ideas")]
+    pub ideas: Option<Vec<Idea>>,
     #[serde(default)]
     pub last_election: Option<String>,
     #[serde(default)]
     pub elections_allowed: Option<bool>,
+    // Hired advisors, filled from the country's characters block by the
+    // regex extraction pass
+    #[serde(default, skip_deserializing)]
+    pub advisors: Vec<Advisor>,
+}
+
+#[derive(Deserialize, Debug, Clone, Serialize, PartialEq)]
+pub struct Advisor {
+    // political_advisor, army_chief, high_command, theorist, ...
+    pub slot: Option<String>,
+    pub character_id: Option<i32>,
+    // From the character database
+    pub name: Option<String>,
+}
+
+impl Politics {
+    // Popularity of the party currently in power; `None` when the ruling
+    // party isn't one of the parties the save lists
+    pub fn ruling_party_popularity(&self) -> Option<f64> {
+        let ruling_party = self.ruling_party.as_deref()?;
+        self.parties.as_ref()?.get(ruling_party)?.popularity
+    }
+
+    // The vanilla four popularities with anything the save leaves out as
+    // 0.0, so they can be summed and compared without null checks
+    pub fn popularities(&self) -> Popularities {
+        let popularity = |ideology: &str| {
+            self.parties
+                .as_ref()
+                .and_then(|parties| parties.get(ideology))
+                .and_then(|party| party.popularity)
+                .unwrap_or(0.0)
+        };
+        Popularities {
+            democratic: popularity("democratic"),
+            communism: popularity("communism"),
+            fascism: popularity("fascism"),
+            neutrality: popularity("neutrality"),
+        }
+    }
+
+    // Map colour of the ruling ideology as a #rrggbb string, for the addon's
+    // UI; None when the ruling party isn't one of the vanilla four
+    pub fn ideology_color(&self) -> Option<&'static str> {
+        let ruling_party = self.ruling_party.as_deref()?;
+        IDEOLOGY_COLORS
+            .iter()
+            .find(|(ideology, _)| *ideology == ruling_party)
+            .map(|(_, color)| *color)
+    }
+
+    // The idea holding a law slot (a LAW_CATEGORIES category), if any
+    pub fn law(&self, category: &str) -> Option<String> {
+        self.ideas
+            .as_ref()?
+            .iter()
+            .find(|idea| idea.category.as_deref() == Some(category))
+            .map(|idea| idea.id.clone())
+    }
+}
+
+// The vanilla ideology colours from common/ideologies/00_ideologies.txt
+const IDEOLOGY_COLORS: &[(&str, &str)] = &[
+    ("democratic", "#0000ff"),
+    ("communism", "#ff0000"),
+    ("fascism", "#964b00"),
+    ("neutrality", "#808080"),
+];
+
+// A single save has no history, so this is a snapshot rather than a trend;
+// diffing two saves gives the drift
+#[derive(Debug, Clone, Copy, Serialize, Default, PartialEq)]
+pub struct Popularities {
+    pub democratic: f64,
+    pub communism: f64,
+    pub fascism: f64,
+    pub neutrality: f64,
 }
 
 #[derive(Deserialize, Debug, Clone, Serialize)]
-pub struct Parties {
+pub struct Idea {
+    pub id: String,
     #[serde(default)]
+    pub category: Option<String>,
+}
+
+// Vanilla law ideas, which the save lists alongside national spirits without
+// saying which slot they occupy
+const LAW_CATEGORIES: &[(&str, &[&str])] = &[
+    ("economy", &[
+        "undisturbed_isolation", "isolation", "civilian_economy", "low_economic_mobilisation",
+        "partial_economic_mobilisation", "war_economy", "tot_economic_mobilisation",
+    ]),
+    ("trade_laws", &["closed_economy", "limited_exports", "export_focus", "free_trade"]),
+    ("mobilization_laws", &[
+        "disarmed_nation", "volunteer_only", "limited_conscription", "extensive_conscription",
+        "service_by_requirement", "all_adults_serve", "scraping_the_barrel",
+    ]),
+];
+
+fn law_category(id: &str) -> Option<String> {
+    LAW_CATEGORIES
+        .iter()
+        .find(|(_, ids)| ids.contains(&id))
+        .map(|(category, _)| category.to_string())
+}
+
+// The save stores ideas as a flat list of ids (`ideas={ a b c }`) with no
+// category, so laws are categorised from the vanilla table and everything
+// else (national spirits, designers, ...) is left as `None`
+fn deserialize_ideas<'de, D>(deserializer: D) -> Result<Option<Vec<Idea>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let ids: Option<Vec<String>> = Option::deserialize(deserializer)?;
+    Ok(ids.map(|ids| {
+        ids.into_iter()
+            .map(|id| {
+                let category = law_category(&id);
+                Idea { id, category }
+            })
+            .collect()
+    }))
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct Parties {
     pub democratic: Option<Party>,
-    #[serde(default)]
     pub communism: Option<Party>,
-    #[serde(default)]
     pub fascism: Option<Party>,
-    #[serde(default)]
     pub neutrality: Option<Party>,
+    // Ideologies added by mods, keyed by the save's id. Flattened so they
+    // sit next to the vanilla four in the output.
+    #[serde(flatten)]
+    pub extras: HashMap<String, Party>,
+}
+
+// Hand-written because `#[serde(flatten)]` buffers values, and the buffered
+// save scalars no longer deserialize as numbers. Every key in `parties` is
+// an ideology, so each value is read straight into a Party.
+impl<'de> Deserialize<'de> for Parties {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct PartiesVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for PartiesVisitor {
+            type Value = Parties;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a map of ideology to party")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Parties, A::Error>
+            where
+                A: serde::de::MapAccess<'de>,
+            {
+                let mut parties = Parties::default();
+                while let Some(ideology) = map.next_key::<String>()? {
+                    let party = map.next_value::<Party>()?;
+                    match ideology.as_str() {
+                        "democratic" => parties.democratic = Some(party),
+                        "communism" => parties.communism = Some(party),
+                        "fascism" => parties.fascism = Some(party),
+                        "neutrality" => parties.neutrality = Some(party),
+                        _ => {
+                            parties.extras.insert(ideology, party);
+                        }
+                    }
+                }
+                Ok(parties)
+            }
+        }
+
+        deserializer.deserialize_map(PartiesVisitor)
+    }
+}
+
+impl Parties {
+    pub fn get(&self, ideology: &str) -> Option<&Party> {
+        match ideology {
+            "democratic" => self.democratic.as_ref(),
+            "communism" => self.communism.as_ref(),
+            "fascism" => self.fascism.as_ref(),
+            "neutrality" => self.neutrality.as_ref(),
+            _ => self.extras.get(ideology),
+        }
+    }
 }
 
 #[derive(Deserialize, Debug, Clone, Serialize)]
@@ -111,6 +662,9 @@ pub struct CountryLeader {
     pub ideology: Option<String>,
     #[serde(default)]
     pub character: Option<Character>,
+    // Filled in from the character database, where the save keeps them
+    #[serde(default)]
+    pub traits: Vec<String>,
 }
 
 #[derive(Deserialize, Debug, Clone, Serialize)]
@@ -0,0 +1,46 @@
+use thiserror::Error;
+
+use crate::PartialGameData;
+
+// Everything that can go wrong between reading a save and writing its JSON,
+// split so callers can tell a missing file from a bad save from an IO failure
+#[derive(Debug, Error)]
+pub enum ParserError {
+    #[error("Save file '{0}' not found!")]
+    SaveNotFound(String),
+
+    #[error("failed to decode save: {0}")]
+    Decode(#[from] hoi4save::Hoi4Error),
+
+    #[error("binary (ironman) save; pass --tokens <file> to decode it")]
+    MissingTokens,
+
+    #[error("failed to decompress gzipped save: {0}")]
+    Gzip(String),
+
+    // The --timeout budget ran out before extraction finished
+    #[error("gave up after {0:?}; the save took longer than the timeout to extract")]
+    Timeout(std::time::Duration),
+
+    // --validate found output that doesn't match --schema
+    #[error("output doesn't match the schema: {0}")]
+    Invalid(String),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error("failed to load tokens from {path}: {message}")]
+    TokenLoad { path: String, message: String },
+
+    // The save decoded but didn't deserialize; `data` holds what the regex
+    // passes recovered anyway
+    #[error("failed to parse save ({message}); only partial data was recovered")]
+    Partial { data: Box<PartialGameData>, message: String },
+}
+
+// JSON serialization only fails on the underlying writer, so it counts as IO
+impl From<serde_json::Error> for ParserError {
+    fn from(err: serde_json::Error) -> Self {
+        ParserError::Io(err.into())
+    }
+}
@@ -0,0 +1,1231 @@
+use std::collections::{BTreeMap, HashMap};
+use regex::Regex;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+use serde::Serialize;
+
+use crate::ParserError;
+use crate::enhanced_country::{ActiveDecision, Advisor, AiStrategy, Expeditionary, Industry, ResearchSlot, Technology};
+
+// Brace depth while scanning forward through save text. Braces inside
+// double-quoted strings (names can contain them) don't count, and a
+// backslash-escaped quote doesn't end the string.
+#[derive(Debug, Clone, Copy, Default)]
+struct Depth {
+    depth: i32,
+    in_quote: bool,
+    escaped: bool,
+}
+
+impl Depth {
+    fn step(&mut self, b: u8) {
+        if self.in_quote {
+            match b {
+                _ if self.escaped => self.escaped = false,
+                b'\\' => self.escaped = true,
+                b'"' => self.in_quote = false,
+                _ => {}
+            }
+            return;
+        }
+        match b {
+            b'"' => self.in_quote = true,
+            b'{' => self.depth += 1,
+            b'}' => self.depth -= 1,
+            _ => {}
+        }
+    }
+
+    fn advance(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.step(b);
+        }
+    }
+
+    // At the top level of the scanned content, outside any string
+    fn at_top(&self) -> bool {
+        self.depth == 0 && !self.in_quote
+    }
+}
+
+// Given the index just past an opening `{`, return the index of its matching `}`
+pub fn find_block_end(content: &str, start: usize) -> Option<usize> {
+    // Without a deadline the scan can't fail
+    find_block_end_before(content, start, None).unwrap_or_default()
+}
+
+// How many bytes a deadline-checked scan covers between looks at the clock
+const DEADLINE_CHECK_BYTES: usize = 64 * 1024;
+
+// `find_block_end`, giving up with ParserError::Timeout once `deadline`
+// passes. An unclosed block scans to the end of the save, so this is the
+// loop a malformed save can make run for a very long time.
+pub fn find_block_end_before(
+    content: &str,
+    start: usize,
+    deadline: Option<&Deadline>,
+) -> Result<Option<usize>, ParserError> {
+    let mut scan = Depth { depth: 1, ..Depth::default() };
+
+    for (chunk_index, chunk) in content.as_bytes()[start..].chunks(DEADLINE_CHECK_BYTES).enumerate() {
+        if let Some(deadline) = deadline {
+            deadline.check()?;
+        }
+        for (idx, &b) in chunk.iter().enumerate() {
+            scan.step(b);
+            if scan.depth == 0 {
+                return Ok(Some(start + chunk_index * DEADLINE_CHECK_BYTES + idx));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+// The --timeout budget, counted from when the parse started
+#[derive(Debug, Clone, Copy)]
+pub struct Deadline {
+    pub started: Instant,
+    pub timeout: Duration,
+}
+
+impl Deadline {
+    pub fn new(timeout: Duration) -> Self {
+        Deadline { started: Instant::now(), timeout }
+    }
+
+    pub fn check(&self) -> Result<(), ParserError> {
+        if self.started.elapsed() > self.timeout {
+            return Err(ParserError::Timeout(self.timeout));
+        }
+        Ok(())
+    }
+}
+
+// Return the inner content of a `key={...}` block that is a direct child of
+// `content` (i.e. not nested inside another block)
+pub fn find_block<'a>(content: &'a str, key: &str) -> Option<&'a str> {
+    find_blocks_impl(content, key, true).into_iter().next()
+}
+
+// Like `find_block`, but returns every direct child block with this key
+pub fn find_blocks<'a>(content: &'a str, key: &str) -> Vec<&'a str> {
+    find_blocks_impl(content, key, false)
+}
+
+fn find_blocks_impl<'a>(content: &'a str, key: &str, first_only: bool) -> Vec<&'a str> {
+    let bytes = content.as_bytes();
+    let mut blocks = Vec::new();
+    let mut scan = Depth::default();
+    let mut scanned_to = 0;
+
+    for (pos, _) in content.match_indices(key) {
+        if pos < scanned_to {
+            continue;
+        }
+        scan.advance(&bytes[scanned_to..pos]);
+        scanned_to = pos;
+
+        if !scan.at_top() || (pos > 0 && !bytes[pos - 1].is_ascii_whitespace()) {
+            continue;
+        }
+
+        // Expect `key={`, allowing whitespace around the `=`
+        let rest = content[pos + key.len()..].trim_start();
+        let Some(rest) = rest.strip_prefix('=') else {
+            continue;
+        };
+        let Some(rest) = rest.trim_start().strip_prefix('{') else {
+            continue;
+        };
+
+        let content_start = content.len() - rest.len();
+        let Some(content_end) = find_block_end(content, content_start) else {
+            break;
+        };
+        blocks.push(&content[content_start..content_end]);
+        if first_only {
+            break;
+        }
+
+        // Skip over the block we just matched; depth is back to 0 after it
+        scanned_to = content_end + 1;
+    }
+
+    blocks
+}
+
+// Return the scalar value of a `key=value` pair that is a direct child of
+// `content`, with surrounding quotes removed
+pub fn find_value<'a>(content: &'a str, key: &str) -> Option<&'a str> {
+    let bytes = content.as_bytes();
+    let mut scan = Depth::default();
+    let mut scanned_to = 0;
+
+    for (pos, _) in content.match_indices(key) {
+        if pos < scanned_to {
+            continue;
+        }
+        scan.advance(&bytes[scanned_to..pos]);
+        scanned_to = pos;
+
+        if !scan.at_top() || (pos > 0 && !bytes[pos - 1].is_ascii_whitespace()) {
+            continue;
+        }
+
+        let rest = content[pos + key.len()..].trim_start();
+        let Some(rest) = rest.strip_prefix('=') else {
+            continue;
+        };
+        let rest = rest.trim_start();
+
+        if let Some(quoted) = rest.strip_prefix('"') {
+            let mut escaped = false;
+            for (idx, ch) in quoted.char_indices() {
+                match ch {
+                    '\\' if !escaped => escaped = true,
+                    '"' if !escaped => return Some(&quoted[..idx]),
+                    _ => escaped = false,
+                }
+            }
+            return None;
+        }
+        if rest.starts_with('{') {
+            continue;
+        }
+        let end = rest
+            .find(|ch: char| ch.is_whitespace() || ch == '}')
+            .unwrap_or(rest.len());
+        return Some(&rest[..end]);
+    }
+
+    None
+}
+
+// List the `key={...}` blocks that are direct children of `content`, in order
+pub fn child_blocks(content: &str) -> Vec<(&str, &str)> {
+    let bytes = content.as_bytes();
+    let mut children = Vec::new();
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let b = bytes[pos];
+        if b == b'{' {
+            // Anonymous nested block; skip it entirely
+            match find_block_end(content, pos + 1) {
+                Some(end) => pos = end + 1,
+                None => break,
+            }
+            continue;
+        }
+        if b == b'"' {
+            // Quoted scalar; skip to the closing quote so its contents
+            // aren't mistaken for keys or braces
+            let mut escaped = false;
+            pos += 1;
+            while pos < bytes.len() {
+                match bytes[pos] {
+                    _ if escaped => escaped = false,
+                    b'\\' => escaped = true,
+                    b'"' => break,
+                    _ => {}
+                }
+                pos += 1;
+            }
+            pos += 1;
+            continue;
+        }
+        if !(b.is_ascii_alphanumeric() || b == b'_') {
+            pos += 1;
+            continue;
+        }
+
+        let key_start = pos;
+        while pos < bytes.len() && (bytes[pos].is_ascii_alphanumeric() || matches!(bytes[pos], b'_' | b'.' | b'-')) {
+            pos += 1;
+        }
+        let key = &content[key_start..pos];
+
+        let rest = content[pos..].trim_start();
+        let Some(rest) = rest.strip_prefix('=') else {
+            continue;
+        };
+        let after_eq = rest.trim_start();
+        match after_eq.strip_prefix('{') {
+            Some(inner) => {
+                let inner_start = content.len() - inner.len();
+                let Some(end) = find_block_end(content, inner_start) else {
+                    break;
+                };
+                children.push((key, &content[inner_start..end]));
+                pos = end + 1;
+            }
+            None => {
+                // Scalar value; skip to just past `=`
+                pos = content.len() - rest.len();
+            }
+        }
+    }
+
+    children
+}
+
+// Undo backslash escaping inside a quoted save value (`\"` and `\\`)
+pub fn unescape_quoted(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\\' {
+            if let Some(next) = chars.next() {
+                unescaped.push(next);
+                continue;
+            }
+        }
+        unescaped.push(ch);
+    }
+    unescaped
+}
+
+// Look for the unique pattern: TAG={\n\t\tinstances_counter=
+// This guarantees we're in the actual country section. Indentation and
+// line endings vary between exports, so allow any whitespace and CRLF.
+// Tags are three letters, or a letter and two digits for the dynamic
+// countries civil wars and releases create (D01, C12).
+static COUNTRY_SECTION_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?m)^[ \t]+([A-Z]{3}|[A-Z]\d{2})[ \t]*=[ \t]*\{[ \t]*\r?\n\s*instances_counter[ \t]*=").unwrap()
+});
+
+// The per-country sections of a save: the inner content of each closed
+// `TAG={...}` block, keyed by tag, and the countries whose block never closes
+// (the save was cut off mid-write, e.g. by a crashed game) with the offset
+// their `TAG={` line starts at, so they can be reported and cut off
+#[derive(Debug, Clone, Default)]
+pub struct CountrySections<'a> {
+    pub complete: Vec<(String, &'a str)>,
+    pub truncated: Vec<(String, usize)>,
+}
+
+// Split the save into its country sections. Fails only when `deadline`
+// passes mid-scan.
+pub fn find_country_sections<'a>(
+    save_content: &'a str,
+    deadline: Option<&Deadline>,
+) -> Result<CountrySections<'a>, ParserError> {
+    let mut sections = Vec::new();
+    let mut truncated = Vec::new();
+    for cap in COUNTRY_SECTION_REGEX.captures_iter(save_content) {
+        let header = cap.get(0).unwrap();
+        let tag = cap.get(1).unwrap();
+
+        // Start after "TAG={" and count braces to find the end of this country's data.
+        // The section itself is the inner content of the country block.
+        let search_start = tag.end() + save_content[tag.end()..].find('{').unwrap() + 1;
+        let Some(country_end) = find_block_end_before(save_content, search_start, deadline)? else {
+            truncated.push((tag.as_str().to_string(), header.start()));
+            continue;
+        };
+
+        sections.push((tag.as_str().to_string(), &save_content[search_start..country_end]));
+    }
+
+    progress!("Found {} countries with instances_counter pattern", sections.len());
+    Ok(CountrySections { complete: sections, truncated })
+}
+
+// The save up to `cut` (the start of a truncated country's `TAG={` line),
+// with the blocks still open there (`countries={`, ...) closed, so the rest
+// of the save parses as if that country and everything after it were never
+// written
+pub fn cut_truncated(save_content: &str, cut: usize) -> String {
+    let kept = &save_content[..cut];
+    let mut scan = Depth::default();
+    scan.advance(kept.as_bytes());
+
+    let mut repaired = String::with_capacity(cut + 2 * scan.depth.max(0) as usize);
+    repaired.push_str(kept);
+    for _ in 0..scan.depth {
+        repaired.push_str("}\n");
+    }
+    repaired
+}
+
+// Run `extract` over every country section, spread across threads. Sections
+// are independent, so each thread takes a contiguous chunk; collecting into
+// a BTreeMap keyed by tag keeps the result identical to a serial pass.
+pub fn map_countries<T, F>(country_sections: &[(String, &str)], extract: F) -> BTreeMap<String, T>
+where
+    T: Send,
+    F: Fn(&str, &str) -> Option<T> + Sync,
+{
+    let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+    let chunk_size = country_sections.len().div_ceil(threads).max(1);
+    let extract = &extract;
+
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = country_sections
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .filter_map(|(tag, section)| Some((tag.clone(), extract(tag, section)?)))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("country extraction thread panicked"))
+            .collect()
+    })
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UnitSummary {
+    pub divisions: u32,
+    pub army_manpower: u64,
+    // Fleets sit in the same units block, one `ship={...}` per vessel
+    pub ships: u32,
+}
+
+static DIVISION_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\bdivision=\{").unwrap());
+static SHIP_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\bship=\{").unwrap());
+static ARMY_MANPOWER_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"army_manpower_value=\{\s*value=\{\s*tag=\S+\s+value=(\d+)").unwrap());
+
+pub fn extract_units(country_sections: &[(String, &str)]) -> BTreeMap<String, UnitSummary> {
+    let units_by_country = map_countries(country_sections, |_, section| {
+        let units = find_block(section, "units")?;
+
+        let mut summary = UnitSummary {
+            divisions: DIVISION_REGEX.find_iter(units).count() as u32,
+            ships: SHIP_REGEX.find_iter(units).count() as u32,
+            ..Default::default()
+        };
+
+        for cap in ARMY_MANPOWER_REGEX.captures_iter(units) {
+            if let Ok(value) = cap[1].parse::<u64>() {
+                summary.army_manpower += value;
+            }
+        }
+
+        Some(summary)
+    });
+
+    progress!("Extracted unit counts for {} countries", units_by_country.len());
+    units_by_country
+}
+
+// Lent divisions stay in the sender's units block but carry the commanding
+// country as their logical_country, so any division whose logical_country
+// isn't the section's own tag is counted as lent to that country
+pub fn extract_expeditionary(country_sections: &[(String, &str)]) -> BTreeMap<String, Vec<Expeditionary>> {
+    let expeditionary_by_country = map_countries(country_sections, |tag, section| {
+        let units = find_block(section, "units")?;
+
+        let mut lent: BTreeMap<&str, u32> = BTreeMap::new();
+        for division in find_blocks(units, "division") {
+            if let Some(to) = find_value(division, "logical_country").filter(|to| *to != tag && is_country_tag(to)) {
+                *lent.entry(to).or_default() += 1;
+            }
+        }
+
+        let expeditionary: Vec<Expeditionary> = lent
+            .into_iter()
+            .map(|(to, divisions)| Expeditionary { to: to.to_string(), divisions })
+            .collect();
+        (!expeditionary.is_empty()).then_some(expeditionary)
+    });
+
+    progress!("Found expeditionary forces for {} countries", expeditionary_by_country.len());
+    expeditionary_by_country
+}
+
+static PUPPET_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\bpuppet=\{").unwrap());
+
+// Subject relationships sit in the countries' diplomacy blocks as
+// `puppet={ autonomy_state="autonomy_dominion" first="ENG" second="CAN" }`,
+// with `first` the overlord. Both sides usually list the same relation.
+// Returns each subject's overlord.
+pub fn extract_overlords(country_sections: &[(String, &str)]) -> BTreeMap<String, String> {
+    let mut overlords = BTreeMap::new();
+    for (_, section) in country_sections {
+        for puppet in PUPPET_REGEX.find_iter(section) {
+            let Some(end) = find_block_end(section, puppet.end()) else {
+                continue;
+            };
+            let relation = &section[puppet.end()..end];
+            let (Some(overlord), Some(subject)) = (find_value(relation, "first"), find_value(relation, "second")) else {
+                continue;
+            };
+            if is_country_tag(overlord) && is_country_tag(subject) && overlord != subject {
+                overlords.entry(subject.to_string()).or_insert_with(|| overlord.to_string());
+            }
+        }
+    }
+
+    progress!("Found {} subject countries", overlords.len());
+    overlords
+}
+
+// Three characters, a letter then letters or digits: vanilla tags are
+// three letters, dynamic ones (civil wars, releasables) look like D01 or C12
+pub fn is_country_tag(tag: &str) -> bool {
+    let bytes = tag.as_bytes();
+    bytes.len() == 3
+        && bytes[0].is_ascii_uppercase()
+        && bytes[1..].iter().all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+}
+
+// Air wings aren't in the country block but in the top-level strategic_air
+// block, grouped by owner: `strategic_air={ GER={ air_wing_pool={ air_wings={
+// count=70 ... } } } }`. Returns the total aircraft per country.
+pub fn extract_planes(save_content: &str) -> BTreeMap<String, u32> {
+    let mut planes_by_country = BTreeMap::new();
+    let Some(strategic_air) = find_block(save_content, "strategic_air") else {
+        progress!("No strategic_air block found");
+        return planes_by_country;
+    };
+
+    for (tag, air) in child_blocks(strategic_air) {
+        if !is_country_tag(tag) {
+            continue;
+        }
+        let planes: u32 = find_blocks(air, "air_wing_pool")
+            .into_iter()
+            .flat_map(|pool| find_blocks(pool, "air_wings"))
+            .filter_map(|wing| find_value(wing, "count")?.parse::<u32>().ok())
+            .sum();
+        planes_by_country.insert(tag.to_string(), planes);
+    }
+
+    progress!("Extracted air wings for {} countries", planes_by_country.len());
+    planes_by_country
+}
+
+// Strategic regions where each country holds air superiority. The save
+// doesn't store superiority itself, but every wing on a mission records the
+// region it flies over and its effective_air_superiority there, so each
+// country's wings are summed per region and the country with the highest
+// positive total holds it (a tie means nobody does). Regions are listed by id.
+pub fn extract_air_superiority(save_content: &str) -> BTreeMap<String, Vec<String>> {
+    let mut superiority_by_region: BTreeMap<u32, BTreeMap<&str, f64>> = BTreeMap::new();
+    if let Some(strategic_air) = find_block(save_content, "strategic_air") {
+        for (tag, air) in child_blocks(strategic_air) {
+            if !is_country_tag(tag) {
+                continue;
+            }
+            let wings = find_blocks(air, "air_wing_pool").into_iter().flat_map(|pool| find_blocks(pool, "air_wings"));
+            for mission in wings.filter_map(|wing| find_block(wing, "mission")) {
+                let region = find_value(mission, "strategic_region").and_then(|region| region.parse::<u32>().ok());
+                let superiority = find_value(mission, "effective_air_superiority")
+                    .and_then(|superiority| superiority.parse::<f64>().ok());
+                if let (Some(region), Some(superiority)) = (region, superiority) {
+                    *superiority_by_region.entry(region).or_default().entry(tag).or_default() += superiority;
+                }
+            }
+        }
+    }
+
+    let mut regions_by_country: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (region, totals) in &superiority_by_region {
+        let mut ranked: Vec<(&str, f64)> = totals.iter().map(|(tag, total)| (*tag, *total)).collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let (leader, best) = ranked[0];
+        let contested = ranked.get(1).is_some_and(|(_, second)| *second >= best);
+        if best > 0.0 && !contested {
+            regions_by_country.entry(leader.to_string()).or_default().push(region.to_string());
+        }
+    }
+
+    progress!("Found air superiority in {} strategic regions", regions_by_country.values().map(Vec::len).sum::<usize>());
+    regions_by_country
+}
+
+// The top-level equipments block names every equipment id in the game:
+// `equipments={ infantry_equipment_1={ id={ id=3 type=70 } ... } ... }`
+fn equipment_types(save_content: &str) -> HashMap<&str, &str> {
+    let mut types = HashMap::new();
+    let Some(database) = find_block(save_content, "equipments") else {
+        return types;
+    };
+    for (name, body) in child_blocks(database) {
+        if let Some(id) = find_block(body, "id").and_then(|id| find_value(id, "id")) {
+            types.insert(id, name);
+        }
+    }
+    types
+}
+
+// infantry_equipment_1 -> infantry_equipment; a type without a version
+// number is its own archetype
+fn equipment_archetype(name: &str) -> &str {
+    match name.rsplit_once('_') {
+        Some((archetype, version)) if !version.is_empty() && version.bytes().all(|b| b.is_ascii_digit()) => archetype,
+        _ => name,
+    }
+}
+
+// Stockpiled equipment from each country's `production={ equipments={
+// equipment={ id={ id=3 type=70 } amount=120.000 } ... } }`, keyed by
+// archetype, or by the individual type when `detail` is set
+pub fn extract_equipment(
+    save_content: &str,
+    country_sections: &[(String, &str)],
+    detail: bool,
+) -> BTreeMap<String, HashMap<String, f64>> {
+    let types = equipment_types(save_content);
+    let equipment_by_country = map_countries(country_sections, |_, section| {
+        let stockpile = find_block(section, "production").and_then(|production| find_block(production, "equipments"))?;
+
+        let mut equipment: HashMap<String, f64> = HashMap::new();
+        for entry in find_blocks(stockpile, "equipment") {
+            let name = find_block(entry, "id").and_then(|id| find_value(id, "id")).and_then(|id| types.get(id));
+            let amount = find_value(entry, "amount").and_then(|amount| amount.parse::<f64>().ok());
+            if let (Some(name), Some(amount)) = (name, amount) {
+                let key = if detail { name } else { equipment_archetype(name) };
+                *equipment.entry(key.to_string()).or_default() += amount;
+            }
+        }
+        (!equipment.is_empty()).then_some(equipment)
+    });
+
+    progress!("Extracted equipment stockpiles for {} countries", equipment_by_country.len());
+    equipment_by_country
+}
+
+static RESOURCE_AMOUNT_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^\s*([a-z_]+)=(-?\d+(?:\.\d+)?)\s*$").unwrap());
+
+// Available amount per resource: produced + imported - exported
+pub fn extract_resources(country_sections: &[(String, &str)]) -> BTreeMap<String, HashMap<String, f64>> {
+    let resources_by_country = map_countries(country_sections, |_, section| {
+        let resources = find_block(section, "resources")?;
+
+        let mut available: HashMap<String, f64> = HashMap::new();
+        for (key, sign) in [("produced", 1.0), ("imported", 1.0), ("exported", -1.0)] {
+            let Some(block) = find_block(resources, key) else {
+                continue;
+            };
+            for cap in RESOURCE_AMOUNT_REGEX.captures_iter(block) {
+                if let Ok(amount) = cap[2].parse::<f64>() {
+                    *available.entry(cap[1].to_string()).or_insert(0.0) += sign * amount;
+                }
+            }
+        }
+
+        (!available.is_empty()).then_some(available)
+    });
+
+    progress!("Extracted resources for {} countries", resources_by_country.len());
+    resources_by_country
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Logistics {
+    pub manpower: Option<u64>,
+    pub fuel: Option<f64>,
+    pub convoys: Option<u32>,
+}
+
+static CONVOY_AMOUNT_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\bamount=(\d+(?:\.\d+)?)").unwrap());
+
+// Manpower, fuel and convoys from the country block. Each may be a plain
+// scalar or, as in current saves, a block: fuel lives in `fuel_status` and
+// convoys are an equipment stockpile (`convoys={ equipment={ ... amount=N } }`).
+// A `manpower={...}` block holds no pool size, so manpower is only read when
+// the save writes it as a scalar.
+pub fn extract_logistics(country_sections: &[(String, &str)]) -> BTreeMap<String, Logistics> {
+    let logistics_by_country = map_countries(country_sections, |_, section| {
+        let number = |content: &str, key: &str| find_value(content, key).and_then(|value| value.parse::<f64>().ok());
+
+        let manpower = number(section, "manpower").map(|manpower| manpower.max(0.0) as u64);
+        let fuel = number(section, "fuel")
+            .or_else(|| find_block(section, "fuel_status").and_then(|status| number(status, "fuel")));
+        let convoys = number(section, "convoys").map(|convoys| convoys as u32).or_else(|| {
+            find_block(section, "convoys").map(|block| {
+                CONVOY_AMOUNT_REGEX
+                    .captures_iter(block)
+                    .filter_map(|cap| cap[1].parse::<f64>().ok())
+                    .sum::<f64>() as u32
+            })
+        });
+
+        (manpower.is_some() || fuel.is_some() || convoys.is_some()).then_some(Logistics { manpower, fuel, convoys })
+    });
+
+    progress!("Extracted logistics for {} countries", logistics_by_country.len());
+    logistics_by_country
+}
+
+// Decisions from the country's decision_status block: timed missions,
+// cooldowns (`decision_to_re_enable`) and targeted decisions. Entries with
+// `days=0` have no running timer and are dropped unless `all` is set;
+// `random_item` entries are the game's RNG bookkeeping and always skipped.
+pub fn extract_decisions(country_sections: &[(String, &str)], all: bool) -> BTreeMap<String, Vec<ActiveDecision>> {
+    let decisions_by_country = map_countries(country_sections, |_, section| {
+        let status = find_block(section, "decision_status")?;
+
+        // Targeted decisions get one entry per target, so identical entries
+        // are listed once
+        let mut decisions: Vec<ActiveDecision> = Vec::new();
+        for (kind, entry) in child_blocks(status) {
+            let Some(id) = find_value(entry, "decision").filter(|_| kind != "random_item") else {
+                continue;
+            };
+            let decision = ActiveDecision {
+                id: unescape_quoted(id),
+                days_remaining: find_value(entry, "days")
+                    .and_then(|days| days.parse::<f64>().ok())
+                    .filter(|days| *days > 0.0),
+            };
+            if (all || decision.days_remaining.is_some()) && !decisions.contains(&decision) {
+                decisions.push(decision);
+            }
+        }
+
+        (!decisions.is_empty()).then_some(decisions)
+    });
+
+    progress!("Extracted decisions for {} countries", decisions_by_country.len());
+    decisions_by_country
+}
+
+// Tags the save lists under player_countries, in save order. Single-player
+// saves list just the player; multiplayer ones every country a human holds.
+pub fn extract_human_players(save_content: &str) -> Vec<String> {
+    let Some(block) = find_block(save_content, "player_countries") else {
+        return Vec::new();
+    };
+    let mut players: Vec<String> = Vec::new();
+    for (tag, _) in child_blocks(block) {
+        if is_country_tag(tag) && !players.iter().any(|player| player == tag) {
+            players.push(tag.to_string());
+        }
+    }
+    players
+}
+
+static GAME_RULE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#""?([\w.]+)"?\s*=\s*"?([^"\s{}]+)"?"#).unwrap());
+
+// The game rules chosen when the campaign started, e.g.
+// `game_rules={ rules={ allow_puppeting="yes" } }` (older saves list the
+// rules straight under game_rules), plus the top-level `difficulty`.
+// None when the save has no game_rules block at all.
+pub fn extract_game_rules(save_content: &str) -> Option<BTreeMap<String, String>> {
+    let block = find_block(save_content, "game_rules")?;
+    let rules = find_block(block, "rules").unwrap_or(block);
+
+    let mut game_rules: BTreeMap<String, String> = GAME_RULE_REGEX
+        .captures_iter(rules)
+        .map(|cap| (cap[1].to_string(), cap[2].to_string()))
+        .collect();
+    if let Some(difficulty) = find_value(save_content, "difficulty") {
+        game_rules.insert("difficulty".to_string(), difficulty.to_string());
+    }
+
+    progress!("Found {} game rules", game_rules.len());
+    Some(game_rules)
+}
+
+// Tech sharing group membership: `tech_sharing_group={ id="commonwealth_research"
+// countries={ "ENG" "CAN" ... } }` at the top level, one block per group.
+// Returns each member's group id, keeping the first group for a country
+// listed in several.
+pub fn extract_tech_sharing(save_content: &str) -> BTreeMap<String, String> {
+    let mut groups = BTreeMap::new();
+    for block in find_blocks(save_content, "tech_sharing_group") {
+        let Some(id) = find_value(block, "id") else {
+            continue;
+        };
+        for tag in find_block(block, "countries").map(quoted_tags).unwrap_or_default() {
+            groups.entry(tag).or_insert_with(|| unescape_quoted(id));
+        }
+    }
+
+    progress!("Found {} countries in tech sharing groups", groups.len());
+    groups
+}
+
+static APPOINTED_ADVISOR_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"\{\s*slot="([^"]*)"\s*character=\{\s*id=(-?\d+)\s+type=-?\d+\s*\}"#).unwrap()
+});
+
+// Advisors currently hired, from `characters={ appointed_advisors={ { slot=
+// character= } ... } }`. Names are left to the caller, which has the
+// character database.
+pub fn extract_advisors(country_sections: &[(String, &str)]) -> BTreeMap<String, Vec<Advisor>> {
+    let advisors_by_country = map_countries(country_sections, |_, section| {
+        let appointed = find_block(section, "characters").and_then(|block| find_block(block, "appointed_advisors"))?;
+        let advisors: Vec<Advisor> = APPOINTED_ADVISOR_REGEX
+            .captures_iter(appointed)
+            .map(|cap| Advisor {
+                slot: Some(cap[1].to_string()),
+                character_id: cap[2].parse().ok(),
+                name: None,
+            })
+            .collect();
+        (!advisors.is_empty()).then_some(advisors)
+    });
+
+    progress!("Extracted advisors for {} countries", advisors_by_country.len());
+    advisors_by_country
+}
+
+// The country's `ai={ ai_strategy={ ... } ... }` entries, in save order
+pub fn extract_ai_strategies(country_sections: &[(String, &str)]) -> BTreeMap<String, Vec<AiStrategy>> {
+    let strategies_by_country = map_countries(country_sections, |_, section| {
+        let ai = find_block(section, "ai")?;
+        let strategies: Vec<AiStrategy> = find_blocks(ai, "ai_strategy")
+            .into_iter()
+            .filter_map(|strategy| {
+                Some(AiStrategy {
+                    r#type: find_value(strategy, "type")?.to_string(),
+                    id: find_value(strategy, "id").map(str::to_string),
+                    target: find_value(strategy, "target").map(str::to_string),
+                    value: find_value(strategy, "value").and_then(|value| value.parse().ok()),
+                })
+            })
+            .collect();
+        (!strategies.is_empty()).then_some(strategies)
+    });
+
+    progress!("Extracted AI strategies for {} countries", strategies_by_country.len());
+    strategies_by_country
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FiredEvent {
+    pub name: String,
+    pub tag: Option<String>,
+    pub date: Option<String>,
+    // Readable title, filled in when localisation is loaded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+// Tokens that turn up in place of event names in some save variants
+pub const DEFAULT_EVENT_IGNORE: &[&str] = &["id", "="];
+
+// Whether a fired event name is save-format noise rather than an event:
+// one of the ignored tokens, a bare number or a single character
+pub fn is_event_noise(name: &str, extra_ignore: &[String]) -> bool {
+    DEFAULT_EVENT_IGNORE.contains(&name)
+        || extra_ignore.iter().any(|token| token == name)
+        || name.chars().count() <= 1
+        || name.chars().all(|ch| ch.is_ascii_digit())
+}
+
+static EVENT_ID_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?:^|\s)id[ \t]*=[ \t]*("(?:[^"\\]|\\.)*"|[^\s{}"]+)"#).unwrap());
+
+// Read fired_event_names in save order, duplicates included. Vanilla saves
+// only store bare `id=X` entries, which carry no tag or date; entries written
+// as blocks (`{ id=X country="TAG" date="..." }`) keep that context.
+pub fn extract_fired_events(save_content: &str) -> Vec<FiredEvent> {
+    let mut events = Vec::new();
+    let Some(block) = find_block(save_content, "fired_event_names") else {
+        return events;
+    };
+
+    // Split into top-level text (bare entries) and nested blocks
+    let mut scan = Depth::default();
+    let mut segment_start = 0;
+    let mut block_start = 0;
+    let mut top_level = Vec::new();
+    let mut entries = Vec::new();
+    for (idx, b) in block.bytes().enumerate() {
+        let was_top = scan.at_top();
+        scan.step(b);
+        match b {
+            b'{' if was_top && scan.depth == 1 => {
+                top_level.push(&block[segment_start..idx]);
+                block_start = idx + 1;
+            }
+            b'}' if scan.at_top() => {
+                entries.push(&block[block_start..idx]);
+                segment_start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    if scan.at_top() {
+        top_level.push(&block[segment_start..]);
+    }
+
+    for segment in top_level {
+        for cap in EVENT_ID_REGEX.captures_iter(segment) {
+            let name = cap[1].trim_matches('"');
+            events.push(FiredEvent { name: unescape_quoted(name), tag: None, date: None, title: None });
+        }
+    }
+
+    for entry in entries {
+        let Some(name) = find_value(entry, "id") else {
+            continue;
+        };
+        events.push(FiredEvent {
+            name: unescape_quoted(name),
+            tag: find_value(entry, "country").or_else(|| find_value(entry, "tag")).map(str::to_string),
+            date: find_value(entry, "date").map(str::to_string),
+            title: None,
+        });
+    }
+
+    events
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct War {
+    pub name: Option<String>,
+    pub attackers: Vec<String>,
+    pub defenders: Vec<String>,
+}
+
+impl War {
+    // The other side of the war from `tag`, or nothing when it isn't in it
+    pub fn enemies_of(&self, tag: &str) -> &[String] {
+        if self.attackers.iter().any(|attacker| attacker == tag) {
+            &self.defenders
+        } else if self.defenders.iter().any(|defender| defender == tag) {
+            &self.attackers
+        } else {
+            &[]
+        }
+    }
+}
+
+static QUOTED_TAG_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#""([A-Z][A-Z0-9]{2})""#).unwrap());
+
+// Collect every quoted country tag in a block, keeping first-seen order
+fn quoted_tags(content: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for cap in QUOTED_TAG_REGEX.captures_iter(content) {
+        if !tags.iter().any(|tag| tag == &cap[1]) {
+            tags.push(cap[1].to_string());
+        }
+    }
+    tags
+}
+
+// A block's own name= line, shared by the war and faction passes
+static NAME_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?m)^\s*name="([^"]*)""#).unwrap());
+static WAR_RELATION_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\bwar_relation=\{").unwrap());
+static WAR_FIRST_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"\bfirst="([^"]+)""#).unwrap());
+static WAR_SECOND_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"\bsecond="([^"]+)""#).unwrap());
+
+pub fn extract_wars(save_content: &str) -> Vec<War> {
+    let mut wars = Vec::new();
+
+    // Ongoing wars live in top-level active_war blocks; concluded ones are
+    // kept as previous_war and are deliberately not read here
+    for war_block in find_blocks(save_content, "active_war") {
+        let attackers = find_block(war_block, "attackers").map(quoted_tags).unwrap_or_default();
+        let defenders = find_block(war_block, "defenders").map(quoted_tags).unwrap_or_default();
+
+        // A war with an empty side has been settled but not yet cleaned up
+        if attackers.is_empty() || defenders.is_empty() {
+            continue;
+        }
+
+        wars.push(War {
+            name: NAME_REGEX.captures(war_block).map(|cap| cap[1].to_string()),
+            attackers,
+            defenders,
+        });
+    }
+
+    // Some saves only record wars as pairwise war_relation entries in each
+    // country's diplomacy block, so fall back to those
+    if wars.is_empty() {
+
+        let mut seen = Vec::new();
+        for relation in WAR_RELATION_REGEX.find_iter(save_content) {
+            let Some(end) = find_block_end(save_content, relation.end()) else {
+                continue;
+            };
+            let block = &save_content[relation.end()..end];
+
+            let (Some(first), Some(second)) = (WAR_FIRST_REGEX.captures(block), WAR_SECOND_REGEX.captures(block)) else {
+                continue;
+            };
+            let (first, second) = (first[1].to_string(), second[1].to_string());
+
+            // Each relation is stored on both sides, so only keep one copy
+            let mut pair = [first.clone(), second.clone()];
+            pair.sort();
+            if seen.contains(&pair) {
+                continue;
+            }
+            seen.push(pair);
+
+            let (attacker, defender) = if block.contains("first_was_instigator=yes") {
+                (first, second)
+            } else {
+                (second, first)
+            };
+            wars.push(War {
+                name: None,
+                attackers: vec![attacker],
+                defenders: vec![defender],
+            });
+        }
+    }
+
+    progress!("Extracted {} active wars", wars.len());
+    wars
+}
+
+// Tags of countries whose block carries `capitulated=yes`
+pub fn extract_capitulations(country_sections: &[(String, &str)]) -> Vec<String> {
+    let capitulations: Vec<String> = country_sections
+        .iter()
+        .filter(|(_, section)| find_value(section, "capitulated") == Some("yes"))
+        .map(|(tag, _)| tag.clone())
+        .collect();
+
+    progress!("Found {} capitulated countries", capitulations.len());
+    capitulations
+}
+
+// Countries taking part in the peace conference, if one is running. The
+// block is present but empty between conferences.
+pub fn extract_peace_conference(save_content: &str) -> Vec<String> {
+    find_block(save_content, "peace_conference")
+        .map(quoted_tags)
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Faction {
+    pub name: String,
+    pub leader: String,
+    pub members: Vec<String>,
+}
+
+static FACTION_LEADER_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?m)^\s*(?:faction_)?leader="([^"]+)""#).unwrap());
+
+pub fn extract_factions(save_content: &str) -> Vec<Faction> {
+    let mut factions = Vec::new();
+
+    for faction_block in find_blocks(save_content, "faction") {
+        let mut members = find_block(faction_block, "members").map(quoted_tags).unwrap_or_default();
+
+        // Saves that don't record the leader explicitly list it first
+        let leader = match FACTION_LEADER_REGEX.captures(faction_block) {
+            Some(cap) => cap[1].to_string(),
+            None => match members.first() {
+                Some(tag) => tag.clone(),
+                None => continue,
+            },
+        };
+        if !members.contains(&leader) {
+            members.insert(0, leader.clone());
+        }
+
+        factions.push(Faction {
+            name: NAME_REGEX
+                .captures(faction_block)
+                .map(|cap| cap[1].to_string())
+                .unwrap_or_default(),
+            leader,
+            members,
+        });
+    }
+
+    progress!("Extracted {} factions", factions.len());
+    factions
+}
+
+static TECH_LEVEL_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\blevel=(\d+)").unwrap());
+static RESEARCH_POINTS_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\bresearch_points=(-?\d+(?:\.\d+)?)").unwrap());
+static RESEARCH_PROGRESS_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\bprogress=(-?\d+(?:\.\d+)?)").unwrap());
+
+pub fn extract_technology(country_sections: &[(String, &str)]) -> BTreeMap<String, Technology> {
+    let technology_by_country = map_countries(country_sections, |_, section| {
+        let technology_block = find_block(section, "technology")?;
+
+        let mut technology = Technology::default();
+        let mut research_points: HashMap<&str, f64> = HashMap::new();
+
+        if let Some(technologies) = find_block(technology_block, "technologies") {
+            for (tech, body) in child_blocks(technologies) {
+                let level = TECH_LEVEL_REGEX.captures(body).and_then(|cap| cap[1].parse::<u32>().ok());
+                if level.unwrap_or(0) > 0 {
+                    technology.completed.push(tech.to_string());
+                }
+                if let Some(points) = RESEARCH_POINTS_REGEX.captures(body).and_then(|cap| cap[1].parse::<f64>().ok()) {
+                    research_points.insert(tech, points);
+                }
+            }
+        }
+
+        // Active research is listed per slot; idle slots show up as `empty`
+        let slots = find_block(technology_block, "slots").or_else(|| find_block(section, "research"));
+        if let Some(slots) = slots {
+            for (tech, body) in child_blocks(slots) {
+                if tech == "empty" {
+                    continue;
+                }
+                let progress = RESEARCH_PROGRESS_REGEX
+                    .captures(body)
+                    .and_then(|cap| cap[1].parse::<f64>().ok())
+                    .or_else(|| research_points.get(tech).copied())
+                    .unwrap_or(0.0);
+                technology.in_progress.push(ResearchSlot {
+                    tech: tech.to_string(),
+                    progress,
+                });
+            }
+        }
+
+        Some(technology)
+    });
+
+    progress!("Extracted technology for {} countries", technology_by_country.len());
+    technology_by_country
+}
+
+// Split the top-level states block into (state id, state content) pairs
+pub fn find_state_sections(save_content: &str) -> Vec<(u32, &str)> {
+    let Some(states) = find_block(save_content, "states") else {
+        progress!("No states block found");
+        return Vec::new();
+    };
+
+    let sections: Vec<(u32, &str)> = child_blocks(states)
+        .into_iter()
+        .filter_map(|(id, state)| id.parse::<u32>().ok().map(|id| (id, state)))
+        .collect();
+
+    progress!("Found {} states", sections.len());
+    sections
+}
+
+// A state building's level is stored as one health value per built level,
+// e.g. `arms_factory={ level={ 100.000 100.000 } }` is two factories
+pub fn building_level(buildings: &str, building: &str) -> u32 {
+    find_block(buildings, building)
+        .and_then(|block| find_block(block, "level"))
+        .map(|levels| levels.split_whitespace().count() as u32)
+        .unwrap_or(0)
+}
+
+// Sum factory counts across the states each country owns
+pub fn extract_industry(state_sections: &[(u32, &str)]) -> BTreeMap<String, Industry> {
+    let mut industry_by_owner: BTreeMap<String, Industry> = BTreeMap::new();
+
+    for (_, state) in state_sections {
+        let Some(owner) = find_value(state, "owner") else {
+            continue;
+        };
+        let Some(buildings) = find_block(state, "buildings") else {
+            continue;
+        };
+
+        let industry = industry_by_owner.entry(owner.to_string()).or_default();
+        industry.civilian_factories += building_level(buildings, "industrial_complex");
+        industry.military_factories += building_level(buildings, "arms_factory");
+        industry.dockyards += building_level(buildings, "dockyard");
+    }
+
+    progress!("Extracted industry for {} countries", industry_by_owner.len());
+    industry_by_owner
+}
+
+// Who owns and who holds each state. The save only writes `controller`
+// when it differs from the owner, and only some states carry their
+// victory points
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct StateInfo {
+    pub id: u32,
+    pub owner: Option<String>,
+    pub controller: Option<String>,
+    pub victory_points: Option<u32>,
+    pub buildings: StateBuildings,
+}
+
+// Levels of the non-factory buildings in a state. Forts and naval bases are
+// provincial, so saves that keep them under `provinces` instead leave them 0.
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct StateBuildings {
+    pub infrastructure: u32,
+    pub land_forts: u32,
+    pub coastal_forts: u32,
+    pub anti_air: u32,
+    pub radar: u32,
+    pub air_base: u32,
+    pub naval_base: u32,
+}
+
+impl StateBuildings {
+    fn from_state(state: &str) -> Self {
+        let Some(buildings) = find_block(state, "buildings") else {
+            return StateBuildings::default();
+        };
+        StateBuildings {
+            infrastructure: building_level(buildings, "infrastructure"),
+            land_forts: building_level(buildings, "bunker"),
+            coastal_forts: building_level(buildings, "coastal_bunker"),
+            anti_air: building_level(buildings, "anti_air_building"),
+            radar: building_level(buildings, "radar_station"),
+            air_base: building_level(buildings, "air_base"),
+            naval_base: building_level(buildings, "naval_base"),
+        }
+    }
+}
+
+// Unowned states (sea and wasteland placeholders) are skipped
+pub fn extract_states(state_sections: &[(u32, &str)]) -> Vec<StateInfo> {
+    let states: Vec<StateInfo> = state_sections
+        .iter()
+        .filter_map(|&(id, state)| {
+            let owner = find_value(state, "owner")?;
+            Some(StateInfo {
+                id,
+                owner: Some(owner.to_string()),
+                controller: Some(find_value(state, "controller").unwrap_or(owner).to_string()),
+                victory_points: find_value(state, "victory_points").and_then(|points| points.parse().ok()),
+                buildings: StateBuildings::from_state(state),
+            })
+        })
+        .collect();
+
+    progress!("Extracted {} owned states", states.len());
+    states
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Occupation {
+    // States owned by someone else that this country holds
+    pub controlled_foreign_states: u32,
+    // States this country owns that someone else holds
+    pub occupied_home_states: u32,
+}
+
+pub fn count_occupation(states: &[StateInfo]) -> BTreeMap<String, Occupation> {
+    let mut occupation: BTreeMap<String, Occupation> = BTreeMap::new();
+
+    for state in states {
+        let (Some(owner), Some(controller)) = (&state.owner, &state.controller) else {
+            continue;
+        };
+        if owner == controller {
+            continue;
+        }
+        occupation.entry(controller.clone()).or_default().controlled_foreign_states += 1;
+        occupation.entry(owner.clone()).or_default().occupied_home_states += 1;
+    }
+
+    occupation
+}
@@ -0,0 +1,295 @@
+// Support for externally gzipped saves (`autosave.hoi4.gz`). Ironman saves
+// are zip archives that Hoi4File opens itself; this only covers a plain
+// text or binary save that was run through gzip afterwards.
+//
+// The DEFLATE decoder (RFC 1951) is the straightforward canonical-Huffman
+// one: it favours being short and obviously correct over speed, which is
+// fine for a once-per-run decompression.
+
+use crate::ParserError;
+
+const MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+// Header flag bits (RFC 1952)
+const FHCRC: u8 = 0x02;
+const FEXTRA: u8 = 0x04;
+const FNAME: u8 = 0x08;
+const FCOMMENT: u8 = 0x10;
+
+pub fn is_gzip(bytes: &[u8]) -> bool {
+    bytes.starts_with(&MAGIC)
+}
+
+// Decompress every gzip member in `bytes` (concatenated members are one
+// stream), checking each member's CRC and length
+pub fn decompress(bytes: &[u8]) -> Result<Vec<u8>, ParserError> {
+    let error = |message: &str| ParserError::Gzip(message.to_string());
+    let mut out = Vec::with_capacity(bytes.len() * 4);
+    let mut rest = bytes;
+
+    while is_gzip(rest) {
+        let body = skip_header(rest).ok_or_else(|| error("truncated header"))?;
+        let member_start = out.len();
+        let mut reader = BitReader::new(body);
+        inflate(&mut reader, &mut out, member_start).map_err(error)?;
+
+        let trailer = body.get(reader.pos..reader.pos + 8).ok_or_else(|| error("truncated trailer"))?;
+        let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+        let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+        let member = &out[member_start..];
+        if crc32(member) != crc {
+            return Err(error("CRC mismatch"));
+        }
+        if member.len() as u32 != size {
+            return Err(error("length mismatch"));
+        }
+        rest = &body[reader.pos + 8..];
+    }
+
+    Ok(out)
+}
+
+// Return the data after the member header
+fn skip_header(bytes: &[u8]) -> Option<&[u8]> {
+    // ID1 ID2 CM FLG MTIME(4) XFL OS; 8 is the only compression method
+    if bytes.len() < 10 || bytes[2] != 8 {
+        return None;
+    }
+    let flags = bytes[3];
+    let mut rest = &bytes[10..];
+    if flags & FEXTRA != 0 {
+        let len = usize::from(u16::from_le_bytes([*rest.first()?, *rest.get(1)?]));
+        rest = rest.get(2 + len..)?;
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let end = rest.iter().position(|&b| b == 0)?;
+            rest = &rest[end + 1..];
+        }
+    }
+    if flags & FHCRC != 0 {
+        rest = rest.get(2..)?;
+    }
+    Some(rest)
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut table = [0u32; 256];
+    for (n, entry) in table.iter_mut().enumerate() {
+        let mut c = n as u32;
+        for _ in 0..8 {
+            c = if c & 1 != 0 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 };
+        }
+        *entry = c;
+    }
+    !data.iter().fold(!0u32, |crc, &b| table[((crc ^ u32::from(b)) & 0xFF) as usize] ^ (crc >> 8))
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buffer: u32,
+    count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        BitReader { data, pos: 0, buffer: 0, count: 0 }
+    }
+
+    // DEFLATE packs bits least significant first
+    fn bits(&mut self, n: u32) -> Result<u32, &'static str> {
+        while self.count < n {
+            let byte = *self.data.get(self.pos).ok_or("unexpected end of data")?;
+            self.buffer |= u32::from(byte) << self.count;
+            self.pos += 1;
+            self.count += 8;
+        }
+        let value = self.buffer & ((1u32 << n) - 1);
+        self.buffer >>= n;
+        self.count -= n;
+        Ok(value)
+    }
+
+    // Drop the rest of the current byte; bytes are only ever loaded on
+    // demand, so nothing beyond it has been consumed
+    fn align(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+    }
+}
+
+// Canonical Huffman code: how many codes there are of each length, and
+// the symbols in code order
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0u16; 16];
+        for &len in lengths {
+            counts[usize::from(len)] += 1;
+        }
+        counts[0] = 0;
+
+        let mut offsets = [0u16; 16];
+        for len in 1..15 {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[usize::from(offsets[usize::from(len)])] = symbol as u16;
+                offsets[usize::from(len)] += 1;
+            }
+        }
+        Huffman { counts, symbols }
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, &'static str> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..16 {
+            code |= reader.bits(1)? as i32;
+            let count = i32::from(self.counts[len]);
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("invalid Huffman code")
+    }
+}
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097,
+    6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13,
+];
+// Order the code length code lengths are stored in
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+// Inflate one member onto the end of `out`. Its output starts at
+// `member_start`, and back-references may not reach an earlier member.
+fn inflate(reader: &mut BitReader, out: &mut Vec<u8>, member_start: usize) -> Result<(), &'static str> {
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => stored_block(reader, out)?,
+            1 => {
+                let (literals, distances) = fixed_codes();
+                compressed_block(reader, out, member_start, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(reader)?;
+                compressed_block(reader, out, member_start, &literals, &distances)?;
+            }
+            _ => return Err("invalid block type"),
+        }
+        if last {
+            reader.align();
+            return Ok(());
+        }
+    }
+}
+
+fn stored_block(reader: &mut BitReader, out: &mut Vec<u8>) -> Result<(), &'static str> {
+    reader.align();
+    let header = reader.data.get(reader.pos..reader.pos + 4).ok_or("unexpected end of data")?;
+    let len = u16::from_le_bytes([header[0], header[1]]);
+    let nlen = u16::from_le_bytes([header[2], header[3]]);
+    if len != !nlen {
+        return Err("corrupt stored block length");
+    }
+    let start = reader.pos + 4;
+    let data = reader.data.get(start..start + usize::from(len)).ok_or("unexpected end of data")?;
+    out.extend_from_slice(data);
+    reader.pos = start + usize::from(len);
+    Ok(())
+}
+
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+}
+
+fn dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman), &'static str> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+
+    let mut code_lengths = [0u8; 19];
+    for &index in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[index] = reader.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths);
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let (value, repeat) = match code_length_code.decode(reader)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => (*lengths.last().ok_or("repeat with no previous length")?, 3 + reader.bits(2)?),
+            17 => (0, 3 + reader.bits(3)?),
+            18 => (0, 11 + reader.bits(7)?),
+            _ => return Err("invalid code length symbol"),
+        };
+        lengths.extend(std::iter::repeat_n(value, repeat as usize));
+    }
+    if lengths.len() > literal_count + distance_count {
+        return Err("code lengths overrun");
+    }
+    let (literal_lengths, distance_lengths) = lengths.split_at(literal_count);
+    Ok((Huffman::new(literal_lengths), Huffman::new(distance_lengths)))
+}
+
+fn compressed_block(
+    reader: &mut BitReader,
+    out: &mut Vec<u8>,
+    member_start: usize,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), &'static str> {
+    loop {
+        let symbol = literals.decode(reader)?;
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let index = usize::from(symbol - 257);
+                if index >= LENGTH_BASE.len() {
+                    return Err("invalid length symbol");
+                }
+                let length = usize::from(LENGTH_BASE[index]) + reader.bits(u32::from(LENGTH_EXTRA[index]))? as usize;
+
+                let index = usize::from(distances.decode(reader)?);
+                if index >= DISTANCE_BASE.len() {
+                    return Err("invalid distance symbol");
+                }
+                let distance =
+                    usize::from(DISTANCE_BASE[index]) + reader.bits(u32::from(DISTANCE_EXTRA[index]))? as usize;
+                if distance > out.len() - member_start {
+                    return Err("distance too far back");
+                }
+
+                // Copies may overlap what they produce, so go byte by byte
+                let start = out.len() - distance;
+                for offset in 0..length {
+                    out.push(out[start + offset]);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,1193 @@
+// Library interface to the save extraction. `parse_save` returns the same
+// structure the binary writes out as game_data.json, so other tools (or a
+// server) can embed the parser instead of shelling out to it.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::LazyLock;
+use std::time::Duration;
+use hoi4save::file::Hoi4SliceFile;
+use hoi4save::{Encoding, Hoi4Date, Hoi4File, MeltOptions, PdsDate};
+use regex::Regex;
+use serde::Serialize;
+
+#[macro_use]
+pub mod log;
+
+pub mod enhanced_country;
+use enhanced_country::{Advisor, CharacterId, DatabaseCharacter, EnhancedCountry, EnhancedHoi4Save, DEFAULT_STAT};
+
+pub mod tokens;
+
+pub mod extraction;
+use extraction::{Faction, FiredEvent, StateInfo, War};
+
+pub mod schema;
+
+pub mod diff;
+
+pub mod localization;
+use localization::Localizer;
+
+pub mod ranking;
+
+pub mod gzip;
+use ranking::PowerRank;
+
+pub mod error;
+pub use error::ParserError;
+
+// Written into every output so stale files can be told apart
+pub const PARSER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// Vanilla national focuses take 10 steps of 7 days
+pub const DEFAULT_FOCUS_DAYS: f64 = 70.0;
+
+// How far stability or war support must be from 0.5 to count as moved
+pub const DEFAULT_ACTIVITY_EPSILON: f64 = 1e-6;
+
+// How the fired events are written out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EventFormat {
+    // Deduplicated `{name, tag, date}` entries
+    #[default]
+    Full,
+    // Sorted unique names only
+    Flat,
+    // `{name: times_fired}`
+    Counts,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseOptions {
+    // Restrict the output to these tags
+    pub only: Option<Vec<String>>,
+    // Keep only these keys of each country's data
+    pub fields: Option<Vec<String>>,
+    // Write every country key the schema knows, as null where a country
+    // has nothing, instead of leaving some out
+    pub include_null_fields: bool,
+    // Emit every country, skipping the is_active filter
+    pub include_inactive: bool,
+    // Also treat countries with at least this much political power as active
+    pub min_political_power: Option<f64>,
+    // Tolerance around the default stability/war_support in the active filter
+    pub activity_epsilon: f64,
+    // Length of a national focus, for days_remaining
+    pub focus_days: f64,
+    pub events: EventFormat,
+    // Extra event names to drop, on top of DEFAULT_EVENT_IGNORE
+    pub event_ignore: Vec<String>,
+    // Drop dated events fired more than this many days before the save date
+    pub events_since: Option<u32>,
+    // Emit the top-level state list, which is large
+    pub include_states: bool,
+    // Keep decisions without a running timer
+    pub all_decisions: bool,
+    // Report equipment by individual type rather than by archetype
+    pub equipment_detail: bool,
+    // Emit each country's AI strategy entries, which are verbose
+    pub ai_strategy: bool,
+    // Cap on the countries written out, applied after the active filter
+    pub max_countries: Option<usize>,
+    // Gives focuses, ideas, events and technologies readable names
+    pub localization: Option<Localizer>,
+    // Rank the output countries by ranking::power_score
+    pub power_ranking: bool,
+    // Give up with ParserError::Timeout once extraction has run this long.
+    // Checked between passes and while scanning for the country sections,
+    // where an unclosed block would otherwise scan to the end of the save
+    // once per country.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            only: None,
+            fields: None,
+            include_null_fields: false,
+            include_inactive: false,
+            min_political_power: None,
+            activity_epsilon: DEFAULT_ACTIVITY_EPSILON,
+            focus_days: DEFAULT_FOCUS_DAYS,
+            events: EventFormat::default(),
+            event_ignore: Vec::new(),
+            events_since: None,
+            include_states: false,
+            all_decisions: false,
+            equipment_detail: false,
+            ai_strategy: false,
+            max_countries: None,
+            localization: None,
+            power_ranking: false,
+            timeout: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GameData {
+    pub metadata: Metadata,
+    pub events: Events,
+    pub wars: Vec<War>,
+    pub factions: Vec<Faction>,
+    pub capitulations: Vec<String>,
+    pub peace_conference: Vec<String>,
+    // Only present when requested with include_states
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub states: Option<Vec<StateInfo>>,
+    // Only present when requested with power_ranking
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub power_ranking: Option<Vec<PowerRank>>,
+    pub countries: Vec<CountryData>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Metadata {
+    pub player: String,
+    pub date: String,
+    // The same date as YYYY-MM-DD, which sorts and parses everywhere
+    pub iso_date: String,
+    // When the campaign began (vanilla's 1936.1.1.12 unless a mod moves it)
+    // and how many in-game days have passed since
+    pub start_date: String,
+    pub elapsed_days: i64,
+    pub parser_version: String,
+    // More than one human-held country
+    pub is_multiplayer: bool,
+    // Every human-held country; just the player when the save lists none
+    pub human_players: Vec<String>,
+    // Oddities in the save that didn't stop the parse (malformed tags, ...)
+    pub warnings: Vec<String>,
+    pub total_countries: usize,
+    pub active_countries: usize,
+    // Completed focuses across the countries written out
+    pub completed_focuses: usize,
+    // Game rules and difficulty, so the addon knows e.g. whether historical
+    // focuses are off; left out when the save has no game_rules block
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub game_rules: Option<BTreeMap<String, String>>,
+    // Identify the game build; fields the save doesn't have are left out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ironman: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Events {
+    Full(Vec<FiredEvent>),
+    Flat(Vec<String>),
+    Counts(BTreeMap<String, usize>),
+}
+
+impl Events {
+    pub fn len(&self) -> usize {
+        match self {
+            Events::Full(events) => events.len(),
+            Events::Flat(names) => names.len(),
+            Events::Counts(counts) => counts.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// Sanity-check numbers for --stats
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub wars: usize,
+    pub factions: usize,
+    pub completed_focuses: usize,
+    // Most common ruling party among the output countries, with its count
+    // (ties go to the alphabetically first ideology)
+    pub top_ruling_ideology: Option<(String, usize)>,
+}
+
+impl GameData {
+    pub fn stats(&self) -> Stats {
+        let mut ruling: BTreeMap<&str, usize> = BTreeMap::new();
+        let mut completed_focuses = 0;
+        for country in &self.countries {
+            completed_focuses += country.data["focus"]["completed"].as_array().map_or(0, Vec::len);
+            if let Some(party) = country.data["politics"]["ruling_party"].as_str() {
+                *ruling.entry(party).or_default() += 1;
+            }
+        }
+        let top_ruling_ideology = ruling
+            .into_iter()
+            .rev()
+            .max_by_key(|(_, count)| *count)
+            .map(|(ideology, count)| (ideology.to_string(), count));
+        
+        Stats {
+            wars: self.wars.len(),
+            factions: self.factions.len(),
+            completed_focuses,
+            top_ruling_ideology,
+        }
+    }
+}
+
+// What can still be written out when the full deserialization fails: the
+// header fields plus whatever the regex passes found beforehand
+#[derive(Debug, Clone, Serialize)]
+pub struct PartialGameData {
+    pub partial: bool,
+    pub metadata: Metadata,
+    pub completed_focuses: BTreeMap<String, Vec<String>>,
+    // Character names by country tag
+    pub characters: BTreeMap<String, Vec<String>>,
+}
+
+// The --header-only output
+#[derive(Debug, Clone, Serialize)]
+pub struct HeaderData {
+    pub header_only: bool,
+    // Countries aren't read, so the country and focus counts are 0
+    pub metadata: Metadata,
+}
+
+// A country as written out: the deserialized fields plus the ones injected
+// from the extraction passes (completed focuses, character details, ...)
+#[derive(Debug, Clone, Serialize)]
+pub struct CountryData {
+    pub tag: String,
+    pub data: serde_json::Value,
+}
+
+// Just the metadata, for indexing saves: reads the header fields and skips
+// every per-country pass
+pub fn parse_header(bytes: &[u8], tokens: &HashMap<u16, String>) -> Result<HeaderData, ParserError> {
+    let bytes = decompressed(bytes)?;
+    let save_file = Hoi4File::from_slice(&bytes)?;
+    let text = plain_text(&save_file, &bytes, tokens)?;
+    let save_content = save_text(&text);
+    
+    Ok(HeaderData {
+        header_only: true,
+        metadata: header_metadata(&save_content),
+    })
+}
+
+// Externally gzipped saves are unpacked first; everything after sees the
+// plain save
+fn decompressed(bytes: &[u8]) -> Result<Cow<'_, [u8]>, ParserError> {
+    if gzip::is_gzip(bytes) {
+        progress!("Decompressing gzipped save...");
+        return Ok(Cow::Owned(gzip::decompress(bytes)?));
+    }
+    Ok(Cow::Borrowed(bytes))
+}
+
+// The regex extraction passes work on text, so binary saves are melted first
+fn plain_text<'a>(
+    save_file: &Hoi4SliceFile,
+    bytes: &'a [u8],
+    tokens: &HashMap<u16, String>,
+) -> Result<Cow<'a, [u8]>, ParserError> {
+    match save_file.encoding() {
+        Encoding::Plaintext => Ok(Cow::Borrowed(bytes)),
+        Encoding::Binary => {
+            if tokens.is_empty() {
+                return Err(ParserError::MissingTokens);
+            }
+            progress!("Melting binary save with {} tokens...", tokens.len());
+            let mut melted = Vec::new();
+            save_file.melt(MeltOptions::new(), tokens, &mut melted)?;
+            Ok(Cow::Owned(melted))
+        }
+    }
+}
+
+// Parse a save (plaintext, or binary with token definitions) with the
+// default options
+pub fn parse_save(bytes: &[u8], tokens: &HashMap<u16, String>) -> Result<GameData, ParserError> {
+    parse_save_with(bytes, tokens, &ParseOptions::default())
+}
+
+pub fn parse_save_with(
+    bytes: &[u8],
+    tokens: &HashMap<u16, String>,
+    options: &ParseOptions,
+) -> Result<GameData, ParserError> {
+    let deadline = options.timeout.map(extraction::Deadline::new);
+    let check_timeout = || deadline.as_ref().map_or(Ok(()), extraction::Deadline::check);
+    
+    let bytes = decompressed(bytes)?;
+    let save_file = Hoi4File::from_slice(&bytes)?;
+    let text = plain_text(&save_file, &bytes, tokens)?;
+    let save_content = save_text(&text);
+    check_timeout()?;
+    
+    // Extract completed focuses before main parsing
+    progress!("Extracting completed focuses...");
+    let extraction::CountrySections { complete: country_sections, truncated } =
+        extraction::find_country_sections(&save_content, deadline.as_ref())?;
+    let truncation_warnings: Vec<String> = truncated
+        .iter()
+        .map(|(tag, _)| format!("the save looks truncated: the section for {} never closes, so it was skipped", tag))
+        .collect();
+    for message in &truncation_warnings {
+        warning!("{}", message);
+    }
+    let completed_focuses = extract_completed_focuses(&country_sections);
+    check_timeout()?;
+    
+    // Extract division counts
+    progress!("Extracting units...");
+    let units = extraction::extract_units(&country_sections);
+    let expeditionary = extraction::extract_expeditionary(&country_sections);
+    let planes = extraction::extract_planes(&save_content);
+    let air_superiority = extraction::extract_air_superiority(&save_content);
+    check_timeout()?;
+    
+    // Extract resource stockpiles
+    progress!("Extracting resources...");
+    let resources = extraction::extract_resources(&country_sections);
+    check_timeout()?;
+    
+    // Extract equipment stockpiles
+    progress!("Extracting equipment...");
+    let equipment = extraction::extract_equipment(&save_content, &country_sections, options.equipment_detail);
+    check_timeout()?;
+    
+    // Extract research state
+    progress!("Extracting technology...");
+    let technology = extraction::extract_technology(&country_sections);
+    check_timeout()?;
+    
+    // Extract manpower, fuel and convoys
+    progress!("Extracting logistics...");
+    let logistics = extraction::extract_logistics(&country_sections);
+    check_timeout()?;
+    
+    // Extract timed decisions and cooldowns
+    progress!("Extracting decisions...");
+    let decisions = extraction::extract_decisions(&country_sections, options.all_decisions);
+    check_timeout()?;
+    
+    // Extract hired advisors
+    progress!("Extracting advisors...");
+    let advisors = extraction::extract_advisors(&country_sections);
+    check_timeout()?;
+    
+    // Extract the AI's plans, only when asked for since there are many
+    let ai_strategies = options.ai_strategy.then(|| {
+        progress!("Extracting AI strategies...");
+        extraction::extract_ai_strategies(&country_sections)
+    });
+    check_timeout()?;
+    
+    // Extract tech sharing group membership
+    progress!("Extracting tech sharing groups...");
+    let tech_sharing = extraction::extract_tech_sharing(&save_content);
+    check_timeout()?;
+    
+    // Extract puppet relationships
+    progress!("Extracting subject relationships...");
+    let overlords = extraction::extract_overlords(&country_sections);
+    check_timeout()?;
+    
+    // Extract factory counts from the states each country owns
+    progress!("Extracting industry...");
+    let state_sections = extraction::find_state_sections(&save_content);
+    let industry = extraction::extract_industry(&state_sections);
+    check_timeout()?;
+    
+    // Extract state ownership, if asked for
+    let states = options.include_states.then(|| {
+        progress!("Extracting states...");
+        extraction::extract_states(&state_sections)
+    });
+    let occupation = states.as_deref().map(extraction::count_occupation);
+    check_timeout()?;
+    
+    // Extract fired events, with their tag and date where the save has them
+    progress!("Extracting events...");
+    let mut fired_events = extraction::extract_fired_events(&save_content);
+    check_timeout()?;
+    
+    // Extract the current war list
+    progress!("Extracting wars...");
+    let wars = extraction::extract_wars(&save_content);
+    check_timeout()?;
+    
+    // Extract who has already lost
+    progress!("Extracting capitulations...");
+    let capitulations = extraction::extract_capitulations(&country_sections);
+    let peace_conference = extraction::extract_peace_conference(&save_content);
+    check_timeout()?;
+    
+    // Extract alliances
+    progress!("Extracting factions...");
+    let factions = extraction::extract_factions(&save_content);
+    check_timeout()?;
+    
+    // Extract the character database
+    progress!("Extracting characters...");
+    let characters = extract_characters(&save_content);
+    
+    let listed_players = extraction::extract_human_players(&save_content);
+    check_timeout()?;
+    
+    progress!("Attempting to parse save file...");
+    // A truncated save is parsed from just the part before the unfinished
+    // country, so every complete section still comes through in full
+    let parsed = match truncated.first() {
+        Some((_, cut)) => {
+            let repaired = extraction::cut_truncated(&save_content, *cut);
+            Hoi4File::from_slice(repaired.as_bytes()).and_then(|file| file.parse(tokens))
+        }
+        None => save_file.parse(tokens),
+    };
+    let mut save: EnhancedHoi4Save = match parsed {
+        Ok(save) => save,
+        Err(err) => {
+            let mut data = partial_game_data(&save_content, country_sections.len(), completed_focuses, &characters);
+            data.metadata.warnings.extend(truncation_warnings);
+            return Err(ParserError::Partial { data: Box::new(data), message: err.to_string() });
+        }
+    };
+    check_timeout()?;
+    
+    let mut warnings = validate_countries(&mut save.countries);
+    warnings.extend(truncation_warnings);
+    let start_date = campaign_start(save.start_date, &mut warnings);
+    // Sorted by tag so identical saves give byte-identical output
+    save.countries.sort_by(|(a, _), (b, _)| a.cmp(b));
+    
+    for (tag, country) in save.countries.iter_mut() {
+        if let Some(summary) = units.get(tag.as_str()) {
+            country.divisions = Some(summary.divisions);
+            country.army_manpower = Some(summary.army_manpower);
+            country.ships = Some(summary.ships);
+        }
+        country.planes = planes.get(tag.as_str()).copied();
+        if let Some(regions) = air_superiority.get(tag.as_str()) {
+            country.air_superiority_regions = regions.clone();
+        }
+        if let Some(lent) = expeditionary.get(tag.as_str()) {
+            country.expeditionary = lent.clone();
+        }
+        if let Some(available) = resources.get(tag.as_str()) {
+            country.resources = available.clone();
+        }
+        if let Some(stockpile) = equipment.get(tag.as_str()) {
+            country.equipment = stockpile.clone();
+        }
+        country.technology = technology.get(tag.as_str()).cloned();
+        country.industry = industry.get(tag.as_str()).cloned();
+        if let Some(logistics) = logistics.get(tag.as_str()) {
+            country.manpower = logistics.manpower;
+            country.fuel = logistics.fuel;
+            country.convoys = logistics.convoys;
+        }
+        if let Some(active) = decisions.get(tag.as_str()) {
+            country.decisions = active.clone();
+        }
+        if let Some(hired) = advisors.get(tag.as_str()) {
+            let hired: Vec<Advisor> = hired
+                .iter()
+                .map(|advisor| {
+                    let character = advisor.character_id.and_then(|id| characters.get(&id));
+                    Advisor { name: character.and_then(|c| c.name.clone()), ..advisor.clone() }
+                })
+                .collect();
+            country.high_command = hired
+                .iter()
+                .filter(|advisor| advisor.slot.as_deref().is_some_and(|slot| enhanced_country::HIGH_COMMAND_SLOTS.contains(&slot)))
+                .cloned()
+                .collect();
+            if let Some(politics) = country.politics.as_mut() {
+                politics.advisors = hired;
+            }
+        }
+        country.research_sharing_group = tech_sharing.get(tag.as_str()).cloned();
+        country.overlord = overlords.get(tag.as_str()).cloned();
+        country.subjects = overlords
+            .iter()
+            .filter(|(_, overlord)| *overlord == tag.as_str())
+            .map(|(subject, _)| subject.clone())
+            .collect();
+        if let Some(politics) = &country.politics {
+            country.conscription_law = politics.law("mobilization_laws");
+            country.economy_law = politics.law("economy");
+            country.trade_law = politics.law("trade_laws");
+        }
+        for war in &wars {
+            for enemy in war.enemies_of(tag) {
+                if !country.enemies.contains(enemy) {
+                    country.enemies.push(enemy.clone());
+                }
+            }
+        }
+        country.at_war = !country.enemies.is_empty();
+        if let Some(strategies) = &ai_strategies {
+            country.ai_strategies = Some(strategies.get(tag.as_str()).cloned().unwrap_or_default());
+        }
+        if let Some(occupation) = &occupation {
+            let counts = occupation.get(tag.as_str()).cloned().unwrap_or_default();
+            country.controlled_foreign_states = Some(counts.controlled_foreign_states);
+            country.occupied_home_states = Some(counts.occupied_home_states);
+        }
+    }
+    
+    progress!("Player country: {}", save.player);
+    progress!("Date: {}", save.date.game_fmt());
+    progress!("Total countries: {}", save.countries.len());
+    
+    fired_events.retain(|event| !extraction::is_event_noise(&event.name, &options.event_ignore));
+    // --events-since drops dated events older than the cutoff; the save
+    // doesn't date every event, and those without one are kept
+    if let Some(days) = options.events_since {
+        fired_events.retain(|event| {
+            let fired = event.date.as_deref().and_then(|date| Hoi4Date::parse(date).ok());
+            fired.is_none_or(|fired| days_between(&fired, &save.date) <= i64::from(days))
+        });
+    }
+    
+    // Deduplicated and sorted, so two saves can be diffed meaningfully
+    let events = match options.events {
+        EventFormat::Full => {
+            let unique: BTreeSet<&FiredEvent> = fired_events.iter().collect();
+            let mut events: Vec<FiredEvent> = unique.into_iter().cloned().collect();
+            if let Some(localization) = &options.localization {
+                for event in &mut events {
+                    event.title = localization.event_title(&event.name).map(str::to_string);
+                }
+            }
+            Events::Full(events)
+        }
+        EventFormat::Flat => Events::Flat(count_events(&fired_events).into_keys().map(str::to_string).collect()),
+        EventFormat::Counts => Events::Counts(
+            count_events(&fired_events)
+                .into_iter()
+                .map(|(name, count)| (name.to_string(), count))
+                .collect(),
+        ),
+    };
+    
+    // Filter for active countries (see is_active), unless disabled. The
+    // player's own country is always kept, however quiet it is.
+    let is_player = |tag: &str| tag == save.player;
+    let mut active_countries: Vec<_> = save.countries.iter()
+        .filter(|(tag, country)| {
+            if options.include_inactive || is_player(tag.as_str()) || is_active(country, options.activity_epsilon) {
+                return true;
+            }
+            // Alternative criterion: enough political power to be worth including
+            match (options.min_political_power, country.politics.as_ref().and_then(|p| p.political_power)) {
+                (Some(min), Some(pp)) => pp >= min,
+                _ => false,
+            }
+        })
+        // Restrict to the requested tags when `only` is given
+        .filter(|(tag, _)| match &options.only {
+            Some(only) => only.iter().any(|wanted| wanted == tag.as_str()),
+            None => true,
+        })
+        .collect();
+    
+    // Keep the player and the N-1 countries with the most political power
+    // (ties by tag, so the selection is stable), leaving the survivors in
+    // their original order
+    if let Some(max) = options.max_countries {
+        if active_countries.len() > max {
+            let political_power = |country: &EnhancedCountry| {
+                country.politics.as_ref().and_then(|p| p.political_power).unwrap_or(0.0)
+            };
+            let mut ranked = active_countries.clone();
+            ranked.sort_by(|(a_tag, a), (b_tag, b)| {
+                is_player(b_tag.as_str())
+                    .cmp(&is_player(a_tag.as_str()))
+                    .then_with(|| political_power(b).total_cmp(&political_power(a)))
+                    .then_with(|| a_tag.as_str().cmp(b_tag.as_str()))
+            });
+            let kept: BTreeSet<&str> = ranked.iter().take(max).map(|(tag, _)| tag.as_str()).collect();
+            active_countries.retain(|(tag, _)| kept.contains(tag.as_str()));
+        }
+    }
+    
+    // Player first so consumers don't have to search for it; the rest keep
+    // their order
+    active_countries.sort_by_key(|(tag, _)| !is_player(tag.as_str()));
+    
+    let human_players = human_players(listed_players, &save.player);
+    let metadata = Metadata {
+        player: save.player.clone(),
+        date: save.date.game_fmt().to_string(),
+        iso_date: iso_date(&save.date),
+        start_date: start_date.game_fmt().to_string(),
+        elapsed_days: days_between(&start_date, &save.date),
+        parser_version: PARSER_VERSION.to_string(),
+        is_multiplayer: human_players.len() > 1,
+        human_players,
+        warnings,
+        total_countries: save.countries.len(),
+        active_countries: active_countries.len(),
+        completed_focuses: active_countries
+            .iter()
+            .map(|(tag, _)| completed_focuses.get(tag.as_str()).map_or(0, Vec::len))
+            .sum(),
+        game_rules: extraction::extract_game_rules(&save_content),
+        version: save.version.clone(),
+        ironman: save.is_ironman(),
+        checksum: save.checksum.clone(),
+    };
+    
+    let power_ranking = options.power_ranking.then(|| {
+        ranking::power_ranking(active_countries.iter().map(|(tag, country)| (tag.as_str(), country)))
+    });
+    
+    let mut countries: Vec<CountryData> = active_countries
+        .iter()
+        .map(|(tag, country)| CountryData {
+            tag: tag.as_str().to_string(),
+            data: country_data(
+                country,
+                is_player(tag.as_str()),
+                completed_focuses.get(tag.as_str()),
+                &characters,
+                options,
+            ),
+        })
+        .collect();
+    if options.include_null_fields {
+        let schema = schema::output_schema();
+        if let Some(data_schema) = schema.pointer("/properties/countries/items/properties/data") {
+            for country in &mut countries {
+                schema::fill_nulls(&mut country.data, data_schema);
+            }
+        }
+    }
+    if let Some(fields) = &options.fields {
+        select_fields(&mut countries, fields);
+    }
+    
+    Ok(GameData {
+        metadata,
+        events,
+        wars,
+        factions,
+        capitulations,
+        peace_conference,
+        states,
+        power_ranking,
+        countries,
+    })
+}
+
+// Top-level `key="value"` lines; the header fields sit at the start of the
+// save and the checksum at the end
+static HEADER_FIELD_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?m)^(player|date|start_date|version|checksum)="([^"]*)""#).unwrap());
+
+// Saves without a player_countries block still have the player
+fn human_players(listed: Vec<String>, player: &str) -> Vec<String> {
+    if listed.is_empty() && !player.is_empty() {
+        return vec![player.to_string()];
+    }
+    listed
+}
+
+fn partial_game_data(
+    save_content: &str,
+    total_countries: usize,
+    completed_focuses: BTreeMap<String, Vec<String>>,
+    characters: &HashMap<i32, DatabaseCharacter>,
+) -> PartialGameData {
+    let mut names: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for character in characters.values() {
+        if let (Some(country), Some(name)) = (&character.country, &character.name) {
+            names.entry(country.clone()).or_default().push(name.clone());
+        }
+    }
+    for country_names in names.values_mut() {
+        country_names.sort();
+    }
+    
+    let countries: BTreeSet<&String> = completed_focuses.keys().chain(names.keys()).collect();
+    let mut metadata = header_metadata(save_content);
+    metadata.total_countries = total_countries;
+    // Without the parsed countries there's no activity filter, so this
+    // counts the countries anything was recovered for
+    metadata.active_countries = countries.len();
+    metadata.completed_focuses = completed_focuses.values().map(Vec::len).sum();
+    PartialGameData {
+        partial: true,
+        metadata,
+        completed_focuses,
+        characters: names,
+    }
+}
+
+// Metadata from the header fields alone, for when the save isn't (or
+// can't be) deserialized. The country and focus counts are left at 0.
+fn header_metadata(save_content: &str) -> Metadata {
+    let mut header: HashMap<&str, &str> = HashMap::new();
+    for cap in HEADER_FIELD_REGEX.captures_iter(save_content) {
+        let (Some(key), Some(value)) = (cap.get(1), cap.get(2)) else {
+            continue;
+        };
+        header.entry(key.as_str()).or_insert(value.as_str());
+    }
+    
+    let header_field = |key: &str| header.get(key).map(|value| value.to_string());
+    let listed_players = extraction::extract_human_players(save_content);
+    let human_players = human_players(listed_players, header.get("player").copied().unwrap_or_default());
+    let header_date = |key: &str| header.get(key).and_then(|date| Hoi4Date::parse(date).ok());
+    let date = header_date("date");
+    let mut warnings = Vec::new();
+    let start_date = campaign_start(header_date("start_date"), &mut warnings);
+    Metadata {
+        player: header_field("player").unwrap_or_default(),
+        date: header_field("date").unwrap_or_default(),
+        iso_date: date.map(|date| iso_date(&date)).unwrap_or_default(),
+        start_date: start_date.game_fmt().to_string(),
+        elapsed_days: date.map_or(0, |date| days_between(&start_date, &date)),
+        parser_version: PARSER_VERSION.to_string(),
+        is_multiplayer: human_players.len() > 1,
+        human_players,
+        warnings,
+        total_countries: 0,
+        active_countries: 0,
+        completed_focuses: 0,
+        game_rules: extraction::extract_game_rules(save_content),
+        version: header_field("version"),
+        ironman: None,
+        checksum: header_field("checksum"),
+    }
+}
+
+// Serialize a country and inject the fields the extraction passes found
+pub(crate) fn country_data(
+    country: &EnhancedCountry,
+    is_player: bool,
+    completed: Option<&Vec<String>>,
+    characters: &HashMap<i32, DatabaseCharacter>,
+    options: &ParseOptions,
+) -> serde_json::Value {
+    let mut country_data = serde_json::to_value(country).unwrap();
+    
+    // Flag the human player's country so it doesn't have to be matched
+    // against metadata.player
+    country_data["is_player"] = serde_json::json!(is_player);
+    
+    // With localisation loaded, id lists become `{id, name}` pairs (unknown
+    // ids keep the id as their name)
+    let named = |ids: &[String]| match &options.localization {
+        Some(localization) => ids
+            .iter()
+            .map(|id| serde_json::json!({ "id": id, "name": localization.name(id) }))
+            .collect(),
+        None => serde_json::json!(ids),
+    };
+    
+    country_data["completed_focus_count"] = serde_json::json!(completed.map_or(0, Vec::len));
+    
+    // Inject completed focuses if they exist. They come from the raw focus
+    // block, so they're kept even when the struct has no focus to put them in.
+    if let Some(completed) = completed {
+        match country_data.get_mut("focus") {
+            Some(focus) if focus.is_object() => focus["completed"] = named(completed),
+            _ => country_data["focus"] = serde_json::json!({ "completed": named(completed) }),
+        }
+    }
+    
+    if let Some(localization) = &options.localization {
+        if let Some(technology) = &country.technology {
+            country_data["technology"]["completed"] = named(&technology.completed);
+        }
+        if let Some(ideas) = country_data["politics"]["ideas"].as_array_mut() {
+            for idea in ideas {
+                if let Some(id) = idea["id"].as_str() {
+                    idea["name"] = serde_json::json!(localization.name(id));
+                }
+            }
+        }
+        for law in ["conscription_law", "economy_law", "trade_law"] {
+            if let Some(id) = country_data[law].as_str() {
+                country_data[law] = serde_json::json!({ "id": id, "name": localization.name(id) });
+            }
+        }
+    }
+    
+    // Derived flag so consumers don't have to interpret the raw yes/no
+    if let Some(focus) = &country.focus {
+        country_data["focus"]["is_paused"] = serde_json::json!(focus.is_paused());
+        country_data["focus"]["days_remaining"] = serde_json::json!(focus.days_remaining(options.focus_days));
+        // Tells a reworked or modded tree apart from the generic one
+        country_data["focus"]["focus_tree"] = serde_json::json!(country.focus_tree);
+    }
+    
+    // Slots with nothing being researched in them
+    if let (Some(slots), Some(technology)) = (country.research_slots, &country.technology) {
+        let idle = slots.saturating_sub(technology.in_progress.len() as u32);
+        country_data["idle_research_slots"] = serde_json::json!(idle);
+    }
+    
+    // Saves consumers from cross-referencing ruling_party against parties
+    if let Some(politics) = &country.politics {
+        country_data["politics"]["ruling_party_popularity"] = serde_json::json!(politics.ruling_party_popularity());
+        country_data["politics"]["popularities"] = serde_json::json!(politics.popularities());
+        if let Some(color) = politics.ideology_color() {
+            country_data["ideology_color"] = serde_json::json!(color);
+        }
+    }
+    
+    // Enrich character data from the character database
+    if let Some(politics) = country_data.get_mut("politics") {
+        if let Some(parties) = politics.get_mut("parties").and_then(|parties| parties.as_object_mut()) {
+            for (_, party) in parties {
+                if let Some(country_leaders) = party.get_mut("country_leader") {
+                    if let Some(leaders_array) = country_leaders.as_array_mut() {
+                        for leader in leaders_array {
+                            let db_character = leader
+                                .get("character")
+                                .and_then(|character| character.get("id"))
+                                .and_then(|id| id.as_i64())
+                                .and_then(|id| characters.get(&(id as i32)));
+                            let Some(db_character) = db_character else {
+                                continue;
+                            };
+                            leader["traits"] = serde_json::json!(db_character.leader_traits);
+                            
+                            let character = leader["character"].as_object_mut().unwrap();
+                            if let Some(name) = &db_character.name {
+                                character.insert("name".to_string(), serde_json::json!(name));
+                            }
+                            character.insert("token".to_string(), serde_json::json!(db_character.token));
+                            character.insert("country".to_string(), serde_json::json!(db_character.country));
+                            character.insert("traits".to_string(), serde_json::json!(db_character.traits));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    
+    country_data
+}
+
+// Cut each country's data down to the requested keys. A name no country
+// has is most likely a typo, so it's warned about rather than dropped
+// silently.
+fn select_fields(countries: &mut [CountryData], fields: &[String]) {
+    if !countries.is_empty() {
+        for field in fields {
+            if !countries.iter().any(|country| country.data.get(field.as_str()).is_some()) {
+                warning!("unknown country field '{}' in --fields", field);
+            }
+        }
+    }
+    
+    for country in countries {
+        if let Some(data) = country.data.as_object_mut() {
+            data.retain(|key, _| fields.iter().any(|field| field == key));
+        }
+    }
+}
+
+// Values may contain backslash-escaped quotes, so match escapes as a unit
+static COMPLETED_FOCUS_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"completed="((?:[^"\\]|\\.)+)""#).unwrap());
+
+fn extract_completed_focuses(country_sections: &[(String, &str)]) -> BTreeMap<String, Vec<String>> {
+    // Process each country
+    let completed_by_country = extraction::map_countries(country_sections, |country_tag, country_section| {
+        
+        // Look for focus block within this country's section
+        let Some(focus_content) = extraction::find_block(country_section, "focus") else {
+            if country_section.contains("focus={") {
+                progress!("  {} has a focus block that isn't a direct child of the country", country_tag);
+            }
+            return None;
+        };
+        
+        // Extract completed focuses from this country's focus block
+        let mut completed_focuses = Vec::new();
+        
+        for completed_cap in COMPLETED_FOCUS_REGEX.captures_iter(focus_content) {
+            completed_focuses.push(extraction::unescape_quoted(&completed_cap[1]));
+        }
+        
+        if !completed_focuses.is_empty() {
+            progress!("  {} has {} completed focuses: {:?}", 
+                country_tag, completed_focuses.len(), &completed_focuses);
+            return Some(completed_focuses);
+        }
+        if focus_content.contains("completed") {
+            progress!("  {} has 'completed' in focus but regex didn't match", country_tag);
+            // Show a sample for debugging
+            if let Some(idx) = focus_content.find("completed") {
+                let sample_start = idx.saturating_sub(20);
+                let sample_end = (idx + 50).min(focus_content.len());
+                progress!("    Sample: {:?}", &focus_content[sample_start..sample_end]);
+            }
+        }
+        None
+    });
+    
+    progress!("Total countries with completed focuses: {}", completed_by_country.len());
+    
+    completed_by_country
+}
+
+static CHARACTER_ID_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*id=\{\s*id=(-?\d+)\s+type=(-?\d+)\s*\}").unwrap());
+static TRAITS_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\btraits=\{([^}]*)\}").unwrap());
+
+fn extract_characters(save_content: &str) -> HashMap<i32, DatabaseCharacter> {
+    let mut characters = HashMap::new();
+    
+    // Characters live in character_manager, grouped (historical, generated, ...)
+    let Some(manager) = extraction::find_block(save_content, "character_manager") else {
+        progress!("No character_manager block found");
+        return characters;
+    };
+    
+    for (_, group) in extraction::child_blocks(manager) {
+        for character_block in extraction::find_blocks(group, "character") {
+            let Some(id_cap) = CHARACTER_ID_REGEX.captures(character_block) else {
+                continue;
+            };
+            let (Ok(id), Ok(r#type)) = (id_cap[1].parse::<i32>(), id_cap[2].parse::<i32>()) else {
+                continue;
+            };
+            
+            // Traits can come from leader, advisor and commander roles alike
+            let traits = collect_traits(character_block);
+            let leader_traits = extraction::find_block(character_block, "country_leaders")
+                .map(collect_traits)
+                .unwrap_or_default();
+            
+            let value = |key: &str| {
+                extraction::find_value(character_block, key).map(extraction::unescape_quoted)
+            };
+            
+            characters.insert(id, DatabaseCharacter {
+                id: CharacterId { id, r#type },
+                token: value("token"),
+                template: value("template"),
+                name: value("name"),
+                country: value("country"),
+                country_leader: extraction::find_block(character_block, "country_leaders")
+                    .is_some_and(|leaders| leaders.contains("country_leader")),
+                traits,
+                leader_traits,
+            });
+        }
+    }
+    
+    progress!("Extracted {} characters", characters.len());
+    characters
+}
+
+// Every trait id listed under `block`, once each, in first-seen order
+fn collect_traits(block: &str) -> Vec<String> {
+    let mut traits: Vec<String> = Vec::new();
+    for traits_cap in TRAITS_REGEX.captures_iter(block) {
+        // Ids are usually bare, but some saves quote them
+        for trait_id in traits_cap[1].split_whitespace() {
+            let trait_id = extraction::unescape_quoted(trait_id.trim_matches('"'));
+            if !traits.contains(&trait_id) {
+                traits.push(trait_id);
+            }
+        }
+    }
+    traits
+}
+
+// A country is considered active when it has moved off the game's default
+// stability/war_support (0.5) AND has a focus system in use (a current focus,
+// or focus progress left over from one just finished). Countries sitting
+// at both defaults with no focus are usually uninstantiated or irrelevant.
+// `include_inactive` skips this check; `min_political_power` adds an
+// alternative way in. Values within `epsilon` of the default count as the
+// default, since the save rounds them.
+fn is_active(country: &EnhancedCountry, epsilon: f64) -> bool {
+    // Must have non-default stability/war_support values
+    let moved = |value: f64| (value - DEFAULT_STAT).abs() > epsilon;
+    let has_activity = moved(country.stability) || moved(country.war_support);
+    
+    // Must have either a current focus or be able to do focuses (not just have focus=null)
+    let can_do_focuses = match &country.focus {
+        Some(focus) => {
+            // If current is Some (has a focus) or current is None but progress exists (just finished)
+            focus.current.is_some() || focus.progress.is_some()
+        },
+        None => false, // No focus system at all means inactive country
+    };
+    
+    has_activity && can_do_focuses
+}
+
+// Paradox years count 1 BC as -1 with no year zero, while ISO 8601 uses
+// astronomical numbering (1 BC is 0000), so years before 1 shift by one
+pub fn iso_date(date: &Hoi4Date) -> String {
+    let year = i32::from(date.year());
+    let year = if year < 1 { year + 1 } else { year };
+    let sign = if year < 0 { "-" } else { "" };
+    format!("{}{:04}-{:02}-{:02}", sign, year.abs(), date.month(), date.day())
+}
+
+// The save's start_date, falling back to the vanilla start with a warning
+// for saves that don't record one
+fn campaign_start(start_date: Option<Hoi4Date>, warnings: &mut Vec<String>) -> Hoi4Date {
+    start_date.unwrap_or_else(|| {
+        let vanilla = Hoi4Date::from_ymdh(1936, 1, 1, 12);
+        warnings.push(format!("no start_date in the save; assuming the vanilla {}", vanilla.game_fmt()));
+        vanilla
+    })
+}
+
+// Calendar days from one date to another, ignoring the hour (the game's
+// calendar has no leap years); negative when `to` is the earlier one
+pub fn days_between(from: &Hoi4Date, to: &Hoi4Date) -> i64 {
+    // The binary form counts hours
+    let day = |date: &Hoi4Date| i64::from(date.to_binary()).div_euclid(24);
+    day(to) - day(from)
+}
+
+// Warn about tags that don't look like country tags (they're kept, since
+// mods can add their own), and drop all but the last of any duplicated tag,
+// which is the entry the game itself would end up with
+fn validate_countries(countries: &mut Vec<(String, EnhancedCountry)>) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for (tag, _) in countries.iter() {
+        if !extraction::is_country_tag(tag) {
+            warnings.push(format!("unexpected country tag '{}'", tag));
+        }
+    }
+    
+    let mut seen = HashSet::new();
+    let mut keep: Vec<bool> = countries.iter().rev().map(|(tag, _)| seen.insert(tag.clone())).collect();
+    keep.reverse();
+    for ((tag, _), keep) in countries.iter().zip(&keep) {
+        if !keep {
+            warnings.push(format!("duplicate country tag '{}', keeping the last entry", tag));
+        }
+    }
+    let mut keep = keep.into_iter();
+    countries.retain(|_| keep.next().unwrap_or(true));
+    
+    for message in &warnings {
+        warning!("{}", message);
+    }
+    warnings
+}
+
+// Count how often each event fired
+fn count_events(fired_events: &[FiredEvent]) -> BTreeMap<&str, usize> {
+    let mut counts = BTreeMap::new();
+    for event in fired_events {
+        *counts.entry(event.name.as_str()).or_insert(0) += 1;
+    }
+    counts
+}
+
+// View the save bytes as text for the regex passes. Valid UTF-8 (the normal
+// case) is borrowed in place, so a large save isn't held in memory twice.
+// Anything else is most likely an older Windows-1252 save, which is decoded
+// as such so accented names survive; a save that is UTF-8 apart from a few
+// bad bytes is decoded lossily instead, since reading it as Windows-1252
+// would garble every other non-ASCII character.
+fn save_text(bytes: &[u8]) -> Cow<'_, str> {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Cow::Borrowed(text),
+        Err(_) if has_utf8_sequences(bytes) => {
+            progress!("Save is not valid UTF-8, decoding lossily");
+            String::from_utf8_lossy(bytes)
+        }
+        Err(_) => {
+            progress!("Save is not UTF-8, decoding as Windows-1252");
+            Cow::Owned(decode_windows_1252(bytes))
+        }
+    }
+}
+
+// Whether the bytes contain at least one well-formed multi-byte UTF-8
+// sequence, which Windows-1252 text essentially never does by accident
+fn has_utf8_sequences(bytes: &[u8]) -> bool {
+    let mut rest = bytes;
+    loop {
+        match std::str::from_utf8(rest) {
+            Ok(text) => return !text.is_ascii(),
+            Err(err) => {
+                let (valid, after) = rest.split_at(err.valid_up_to());
+                if !valid.is_ascii() {
+                    return true;
+                }
+                rest = &after[err.error_len().unwrap_or(after.len())..];
+            }
+        }
+    }
+}
+
+// 0x80..=0x9F in Windows-1252; the five unassigned bytes map to the C1
+// controls, as browsers do. Everything else is the same as Latin-1.
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+];
+
+fn decode_windows_1252(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&b| match b {
+            0x80..=0x9F => WINDOWS_1252_HIGH[usize::from(b - 0x80)],
+            _ => char::from(b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn large_utf8_save_is_not_copied() {
+        // About 8 MB of countries, with a non-ASCII name in each so the
+        // UTF-8 check has multi-byte sequences to get through
+        let mut save = String::from("HOI4txt\nplayer=\"GER\"\ndate=\"1936.1.1.12\"\ncountries={\n");
+        while save.len() < 8 * 1024 * 1024 {
+            save.push_str("\tARG={\n\t\tinstances_counter=1\n\t\tname=\"Juan Perón\"\n\t}\n");
+        }
+        save.push_str("}\n");
+        let bytes = save.as_bytes();
+
+        // Neither step makes a second copy of the input: the text the
+        // extraction passes scan is the caller's buffer itself
+        let bytes = decompressed(bytes).unwrap();
+        assert!(matches!(bytes, Cow::Borrowed(_)));
+        let save_file = Hoi4File::from_slice(&bytes).unwrap();
+        let plain = plain_text(&save_file, &bytes, &HashMap::new()).unwrap();
+        assert!(matches!(plain, Cow::Borrowed(_)));
+        let text = save_text(&plain);
+        assert!(matches!(text, Cow::Borrowed(_)));
+        assert_eq!(text.as_ptr(), save.as_ptr());
+        assert_eq!(text.len(), save.len());
+
+        // Only a save that needs decoding gets its own copy
+        assert!(matches!(save_text(b"name=\"Juan Per\xf3n\""), Cow::Owned(_)));
+    }
+
+    #[test]
+    fn iso_date_pads_and_drops_the_hour() {
+        assert_eq!(iso_date(&Hoi4Date::from_ymdh(1936, 1, 1, 12)), "1936-01-01");
+        assert_eq!(iso_date(&Hoi4Date::from_ymdh(1939, 9, 1, 1)), "1939-09-01");
+        assert_eq!(iso_date(&Hoi4Date::from_ymdh(1944, 12, 25, 23)), "1944-12-25");
+        // Four-digit years even before 1000
+        assert_eq!(iso_date(&Hoi4Date::from_ymdh(476, 9, 4, 1)), "0476-09-04");
+    }
+}
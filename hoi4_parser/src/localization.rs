@@ -0,0 +1,101 @@
+// Game localisation, for turning ids like GER_rhineland into readable text.
+// HOI4 keeps it in YAML-like files (`localisation/english/*.yml`):
+//
+//   l_english:
+//    GER_rhineland:0 "Rhineland"
+//
+// They aren't quite YAML (the :0 version suffix, unescaped quotes inside
+// values), so they're read line by line rather than with a YAML parser.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use regex::Regex;
+
+use crate::ParserError;
+
+// `key:0 "value"`, where the version number is optional and the value runs
+// to the last quote on the line (anything after it is a comment)
+static ENTRY_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"^\s*([^\s:#"]+):\d*\s+"(.*)""#).unwrap());
+
+// Key -> text map built from a localisation folder, shared by everything
+// the output annotates (focuses, ideas, events, technologies)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Localizer {
+    entries: HashMap<String, String>,
+}
+
+impl Localizer {
+    // Load every .yml file under `dir` (recursively). Point it at a single
+    // language folder; files are read in path order, so a later file
+    // overrides an earlier one.
+    pub fn load(dir: &str) -> Result<Localizer, ParserError> {
+        let mut files = Vec::new();
+        collect_yml_files(Path::new(dir), &mut files)?;
+        files.sort();
+
+        let mut entries = HashMap::new();
+        for file in &files {
+            let bytes = std::fs::read(file)?;
+            let content = String::from_utf8_lossy(&bytes);
+            let content = content.strip_prefix('\u{feff}').unwrap_or(&content);
+            for line in content.lines() {
+                if let Some(cap) = ENTRY_REGEX.captures(line) {
+                    entries.insert(cap[1].to_string(), normalize(&cap[2]));
+                }
+            }
+        }
+
+        progress!("Loaded {} localisation keys from {} files", entries.len(), files.len());
+        Ok(Localizer { entries })
+    }
+
+    pub fn lookup(&self, key: &str) -> Option<&str> {
+        self.entries.get(key).map(String::as_str)
+    }
+
+    // Readable name for an id, falling back to the id itself
+    pub fn name<'a>(&'a self, id: &'a str) -> &'a str {
+        self.lookup(id).unwrap_or(id)
+    }
+
+    // Events are localised by their title key (`news.1.t`), not their id
+    pub fn event_title(&self, event: &str) -> Option<&str> {
+        self.lookup(&format!("{}.t", event)).or_else(|| self.lookup(event))
+    }
+}
+
+// Drop the colour codes (`§Y` starts a colour, `§!` ends it) and turn the
+// escapes into the characters they stand for
+fn normalize(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '§' => {
+                chars.next();
+            }
+            '\\' => match chars.next() {
+                Some('n') => text.push('\n'),
+                Some('t') => text.push('\t'),
+                Some(other) => text.push(other),
+                None => {}
+            },
+            _ => text.push(ch),
+        }
+    }
+    text
+}
+
+fn collect_yml_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), ParserError> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_yml_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "yml") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
@@ -1,255 +1,337 @@
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::Write;
-use std::env;
-use hoi4save::{Hoi4File, PdsDate};
-use serde_json;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
 
-mod enhanced_country;
-use enhanced_country::{EnhancedHoi4Save, DatabaseCharacter};
+use hoi4_parser::{progress, summary, EventFormat, GameData, ParseOptions, ParserError, Stats};
+use serde::Serialize;
 
-use std::collections::BTreeMap;
-use regex::Regex;
+mod cli;
 
-fn extract_completed_focuses(save_content: &str) -> BTreeMap<String, Vec<String>> {
-    let mut completed_by_country = BTreeMap::new();
-    
-    // Look for the unique pattern: TAG={\n\t\tinstances_counter=
-    // This guarantees we're in the actual country section
-    let country_pattern = Regex::new(r"(?m)^\t([A-Z]{3})=\{\n\t\tinstances_counter=").unwrap();
-    let completed_regex = Regex::new(r#"completed="([^"]+)""#).unwrap();
-    
-    // Find all country sections with this unique pattern
-    let mut country_matches: Vec<(String, usize)> = Vec::new();
-    for cap in country_pattern.captures_iter(save_content) {
-        let tag = cap[1].to_string();
-        let pos = cap.get(0).unwrap().start();
-        country_matches.push((tag, pos));
+// Exit code when only partial data could be written, so callers can tell it
+// apart from a full success (0) and a hard failure (1)
+const EXIT_PARTIAL: i32 = 3;
+
+fn main() {
+    let args = match cli::Args::parse() {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("Error: {}", message);
+            eprintln!("Run with --help to list the available options.");
+            std::process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{}", cli::HELP);
+        return;
     }
+    if args.version {
+        println!("hoi4_parser {}", hoi4_parser::PARSER_VERSION);
+        return;
+    }
+    if args.schema {
+        if let Err(err) = write_json(&hoi4_parser::schema::output_schema(), "-", false) {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+    hoi4_parser::log::set_quiet(args.quiet);
     
-    println!("Found {} countries with instances_counter pattern", country_matches.len());
-    
-    // Process each country
-    for i in 0..country_matches.len() {
-        let (country_tag, start_pos) = &country_matches[i];
-        
-        // Find where this country's section starts (at the TAG={ part)
-        let country_def_start = start_pos + 1; // Skip the initial tab
-        
-        // Find the end of this country's section
-        // Start after "TAG={"
-        let search_start = country_def_start + country_tag.len() + 2;
-        
-        // Count braces to find the end of this country's data
-        let mut brace_count = 1;
-        let mut country_end = search_start;
-        
-        for (idx, ch) in save_content[search_start..].char_indices() {
-            if ch == '{' {
-                brace_count += 1;
-            } else if ch == '}' {
-                brace_count -= 1;
-                if brace_count == 0 {
-                    country_end = search_start + idx;
-                    break;
-                }
-            }
+    if let Some((old, new)) = &args.diff {
+        if let Err(err) = run_diff(&args, old, new) {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
         }
-        
-        // Extract this country's entire section
-        let country_section = &save_content[country_def_start..country_end];
-        
-        // Look for focus block within this country's section
-        if let Some(focus_start) = country_section.find("\t\tfocus={") {
-            // Find the matching closing brace for the focus block
-            let focus_content_start = focus_start + 9; // Skip "\t\tfocus={"
-            let mut brace_count = 1;
-            let mut focus_end = focus_content_start;
-            
-            for (idx, ch) in country_section[focus_content_start..].char_indices() {
-                if ch == '{' {
-                    brace_count += 1;
-                } else if ch == '}' {
-                    brace_count -= 1;
-                    if brace_count == 0 {
-                        focus_end = focus_content_start + idx;
-                        break;
-                    }
-                }
-            }
-            
-            // Extract completed focuses from this country's focus block
-            let focus_content = &country_section[focus_content_start..focus_end];
-            let mut completed_focuses = Vec::new();
-            
-            for completed_cap in completed_regex.captures_iter(focus_content) {
-                completed_focuses.push(completed_cap[1].to_string());
-            }
-            
-            if !completed_focuses.is_empty() {
-                println!("  {} has {} completed focuses: {:?}", 
-                    country_tag, completed_focuses.len(), &completed_focuses);
-                completed_by_country.insert(country_tag.clone(), completed_focuses);
-            } else if focus_content.contains("completed") {
-                println!("  {} has 'completed' in focus but regex didn't match", country_tag);
-                // Show a sample for debugging
-                if let Some(idx) = focus_content.find("completed") {
-                    let sample_start = idx.saturating_sub(20);
-                    let sample_end = (idx + 50).min(focus_content.len());
-                    println!("    Sample: {:?}", &focus_content[sample_start..sample_end]);
-                }
-            }
-        } else {
-            // Try without tabs in case formatting varies
-            if country_section.contains("focus={") {
-                println!("  {} has focus block but not with expected tab formatting", country_tag);
+        return;
+    }
+    
+    // Batch mode reports each file itself and keeps going past failures,
+    // but still exits non-zero if any of them failed
+    if let Some(dir) = &args.batch {
+        match run_batch(&args, dir) {
+            Ok(true) => {}
+            Ok(false) => std::process::exit(1),
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
             }
         }
+        return;
     }
     
-    println!("Total countries with completed focuses: {}", completed_by_country.len());
-    
-    completed_by_country
+    // Any failure (missing save, unreadable file, parse error, write error)
+    // exits non-zero so scripts and CI can tell something went wrong
+    if let Err(err) = run(&args) {
+        eprintln!("Error: {}", err);
+        std::process::exit(if matches!(err, ParserError::Partial { .. }) { EXIT_PARTIAL } else { 1 });
+    }
 }
 
-fn extract_character_names(save_content: &str) -> HashMap<i32, String> {
-    let mut character_names = HashMap::new();
-    
-    // Look for character database entries
-    let character_pattern = Regex::new(r#"character=\{\s*id=\{\s*id=(\d+)\s+type=\d+\s*\}\s*[^}]*?name="([^"]+)""#).unwrap();
-    
-    for cap in character_pattern.captures_iter(save_content) {
-        if let Ok(id) = cap[1].parse::<i32>() {
-            let name = cap[2].to_string();
-            println!("Found character: ID {} -> {}", id, name);
-            character_names.insert(id, name);
-        }
+fn run(args: &cli::Args) -> Result<(), ParserError> {
+    let save_path = args.input.as_str();
+    let output_path = args.output.as_str();
+    if let Some(dir) = &args.output_dir {
+        std::fs::create_dir_all(dir)?;
     }
     
-    println!("Extracted {} character names", character_names.len());
-    character_names
-}
-
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args: Vec<String> = env::args().collect();
+    progress!("Parsing HOI4 save file: {}", save_path);
+    let data = read_save(save_path)?;
     
-    let save_path = if args.len() > 1 {
-        &args[1]
-    } else {
-        "autosave.hoi4"
+    let tokens = load_tokens(args)?;
+    if args.header_only {
+        let header = hoi4_parser::parse_header(&data, &tokens)?;
+        write_json(&header, output_path, args.compact)?;
+        summary!("Metadata extracted to: {}", output_path);
+        return Ok(());
+    }
+    let game_data = match hoi4_parser::parse_save_with(&data, &tokens, &parse_options(args)?) {
+        // Still write what was recovered, so the addon has something to work with
+        Err(ParserError::Partial { data, message }) => {
+            write_json(&data, output_path, args.compact)?;
+            summary!("Partial data extracted to: {}", output_path);
+            return Err(ParserError::Partial { data, message });
+        }
+        result => result?,
     };
-    
-    let output_path = if args.len() > 2 {
-        &args[2]
+    if args.validate {
+        validate_output(&game_data)?;
+    }
+    if args.ndjson {
+        write_ndjson(&game_data, output_path)?;
     } else {
-        "../data/game_data.json"
-    };
-    
-    println!("Parsing HOI4 save file: {}", save_path);
-    
-    if !std::path::Path::new(save_path).exists() {
-        println!("Error: Save file '{}' not found!", save_path);
-        return Ok(());
+        write_json(&game_data, output_path, args.compact)?;
+    }
+    if let Some(dir) = &args.split_countries {
+        write_country_files(&game_data, dir, args.compact)?;
     }
     
-    let data = std::fs::read(save_path)?;
-    let save_content = String::from_utf8_lossy(&data);
-    
-    // Extract completed focuses before main parsing
-    println!("Extracting completed focuses...");
-    let completed_focuses = extract_completed_focuses(&save_content);
+    summary!("Data extracted to: {}", output_path);
+    summary!("Events: {}", game_data.events.len());
+    summary!("Active countries: {}", game_data.countries.len());
+    if args.stats {
+        print_stats(&game_data.stats());
+    }
     
-    // Extract character names
-    println!("Extracting character names...");
-    let character_names = extract_character_names(&save_content);
+    Ok(())
+}
+
+fn print_stats(stats: &Stats) {
+    summary!("Stats:");
+    summary!("  Wars: {}", stats.wars);
+    summary!("  Factions: {}", stats.factions);
+    summary!("  Completed focuses: {}", stats.completed_focuses);
+    match &stats.top_ruling_ideology {
+        Some((ideology, count)) => summary!("  Most common ruling ideology: {} ({} countries)", ideology, count),
+        None => summary!("  Most common ruling ideology: none"),
+    }
+}
+
+// Parse two saves and write the delta between them
+fn run_diff(args: &cli::Args, old_path: &str, new_path: &str) -> Result<(), ParserError> {
+    let tokens = load_tokens(args)?;
+    let options = parse_options(args)?;
     
-    let save_file = Hoi4File::from_slice(&data)?;
-    let resolver = HashMap::<u16, &str>::new();
-    println!("Attempting to parse save file...");
-    let save: EnhancedHoi4Save = save_file.parse(resolver)?;
+    progress!("Parsing old save: {}", old_path);
+    let old = hoi4_parser::parse_save_with(&read_save(old_path)?, &tokens, &options)?;
+    progress!("Parsing new save: {}", new_path);
+    let new = hoi4_parser::parse_save_with(&read_save(new_path)?, &tokens, &options)?;
     
-    println!("Player country: {}", save.player);
-    println!("Date: {}", save.date.game_fmt());
-    println!("Total countries: {}", save.countries.len());
+    let diff = hoi4_parser::diff::diff_saves(&old, &new);
+    write_json(&diff, &args.output, args.compact)?;
     
-    // Filter out "id" and "=" tokens from events
-    let clean_events: Vec<&String> = save.fired_event_names.iter()
-        .filter(|event| *event != "id" && *event != "=")
-        .collect();
+    summary!("Diff from {} to {} written to: {}", diff.from_date, diff.to_date, args.output);
+    Ok(())
+}
+
+// Parse every *.hoi4 (or gzipped *.hoi4.gz) file in `dir`, writing
+// game_data_<save>.json files to the output directory. Returns whether
+// every save succeeded.
+fn run_batch(args: &cli::Args, dir: &str) -> Result<bool, ParserError> {
+    let output_dir = Path::new(&args.output);
+    std::fs::create_dir_all(output_dir)?;
     
-    // Filter for active countries (not default values and can actually do focuses)
-    let active_countries: Vec<_> = save.countries.iter()
-        .filter(|(_, country)| {
-            // Must have non-default stability/war_support values
-            let has_activity = country.stability != 0.5 || country.war_support != 0.5;
-            
-            // Must have either a current focus or be able to do focuses (not just have focus=null)
-            let can_do_focuses = match &country.focus {
-                Some(focus) => {
-                    // If current is Some (has a focus) or current is None but progress exists (just finished)
-                    focus.current.is_some() || focus.progress.is_some()
-                },
-                None => false, // No focus system at all means inactive country
-            };
-            
-            has_activity && can_do_focuses
-        })
+    let mut saves: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && save_name(path).is_some())
         .collect();
+    saves.sort();
+    summary!("Found {} saves in {}", saves.len(), dir);
     
-    // Create output structure
-    let output_data = serde_json::json!({
-        "metadata": {
-            "player": save.player,
-            "date": save.date.game_fmt().to_string(),
-            "total_countries": save.countries.len(),
-            "active_countries": active_countries.len()
-        },
-        "events": clean_events,
-        "countries": active_countries.iter().map(|(tag, country)| {
-            let mut country_data = serde_json::to_value(country).unwrap();
-            
-            // Inject completed focuses if they exist
-            if let Some(completed) = completed_focuses.get(tag.as_str()) {
-                if let Some(focus) = country_data.get_mut("focus") {
-                    focus["completed"] = serde_json::json!(completed);
-                }
-            }
-            
-            // Enrich character data with names
-            if let Some(politics) = country_data.get_mut("politics") {
-                if let Some(parties) = politics.get_mut("parties") {
-                    for (_, party) in parties.as_object_mut().unwrap() {
-                        if let Some(country_leaders) = party.get_mut("country_leader") {
-                            if let Some(leaders_array) = country_leaders.as_array_mut() {
-                                for leader in leaders_array {
-                                    if let Some(character) = leader.get_mut("character") {
-                                        if let Some(id) = character.get("id").and_then(|id| id.as_i64()) {
-                                            if let Some(name) = character_names.get(&(id as i32)) {
-                                                character.as_object_mut().unwrap().insert("name".to_string(), serde_json::json!(name));
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
+    let tokens = load_tokens(args)?;
+    let options = parse_options(args)?;
+    let mut failed = 0;
+    for save in &saves {
+        let name = save_name(save).unwrap_or_default();
+        let output_path = output_dir.join(format!("game_data_{}.json", name));
+        progress!("Parsing HOI4 save file: {}", save.display());
+        
+        let result = std::fs::read(save)
+            .map_err(ParserError::from)
+            .and_then(|data| hoi4_parser::parse_save_with(&data, &tokens, &options))
+            .and_then(|game_data| {
+                if args.validate {
+                    validate_output(&game_data)?;
                 }
-            }
-            
-            serde_json::json!({
-                "tag": tag.as_str(),
-                "data": country_data
+                Ok(game_data)
             })
-        }).collect::<Vec<_>>()
-    });
+            .and_then(|game_data| write_json(&game_data, &output_path.to_string_lossy(), args.compact));
+        match result {
+            Ok(()) => summary!("OK      {} -> {}", save.display(), output_path.display()),
+            Err(err) => {
+                failed += 1;
+                summary!("FAILED  {}: {}", save.display(), err);
+            }
+        }
+    }
     
-    // Write JSON to file
-    let mut file = File::create(output_path)?;
-    file.write_all(serde_json::to_string_pretty(&output_data)?.as_bytes())?;
+    summary!("Parsed {} of {} saves", saves.len() - failed, saves.len());
+    Ok(failed == 0)
+}
+
+// The save's name without its .hoi4 / .hoi4.gz extension, or None for
+// anything else
+fn save_name(path: &Path) -> Option<&str> {
+    let file_name = path.file_name()?.to_str()?;
+    let file_name = file_name.strip_suffix(".gz").unwrap_or(file_name);
+    file_name.strip_suffix(".hoi4")
+}
+
+// "-" reads the save from stdin so it can be piped in
+fn read_save(save_path: &str) -> Result<Vec<u8>, ParserError> {
+    if save_path == "-" {
+        let mut data = Vec::new();
+        std::io::stdin().lock().read_to_end(&mut data)?;
+        return Ok(data);
+    }
+    if !Path::new(save_path).exists() {
+        return Err(ParserError::SaveNotFound(save_path.to_string()));
+    }
+    Ok(std::fs::read(save_path)?)
+}
+
+// Binary (ironman) saves need a token resolver to decode anything
+fn load_tokens(args: &cli::Args) -> Result<HashMap<u16, String>, ParserError> {
+    match &args.tokens {
+        Some(path) => {
+            progress!("Loading token definitions from: {}", path);
+            hoi4_parser::tokens::load_tokens(path)
+        }
+        None => Ok(HashMap::new()),
+    }
+}
+
+fn parse_options(args: &cli::Args) -> Result<ParseOptions, ParserError> {
+    let localization = match &args.localization {
+        Some(dir) => {
+            progress!("Loading localisation from: {}", dir);
+            Some(hoi4_parser::localization::Localizer::load(dir)?)
+        }
+        None => None,
+    };
     
-    println!("Data extracted to: {}", output_path);
-    println!("Events: {}", clean_events.len());
-    println!("Active countries: {}", active_countries.len());
+    Ok(ParseOptions {
+        only: args.only.clone(),
+        fields: args.fields.clone(),
+        include_null_fields: args.include_null_fields,
+        include_inactive: args.include_inactive,
+        min_political_power: args.min_political_power,
+        activity_epsilon: args.activity_epsilon,
+        focus_days: args.focus_days,
+        events: if args.event_counts {
+            EventFormat::Counts
+        } else if args.events_flat {
+            EventFormat::Flat
+        } else {
+            EventFormat::Full
+        },
+        event_ignore: args.event_ignore.clone(),
+        events_since: args.events_since,
+        include_states: args.include_states,
+        all_decisions: args.all_decisions,
+        equipment_detail: args.equipment_detail,
+        ai_strategy: args.ai_strategy,
+        max_countries: args.max_countries,
+        localization,
+        power_ranking: args.power_ranking,
+        timeout: args.timeout.map(std::time::Duration::from_secs_f64),
+    })
+}
+
+// Catch extraction bugs before they reach the addon as malformed JSON
+fn validate_output(game_data: &GameData) -> Result<(), ParserError> {
+    let schema = hoi4_parser::schema::output_schema();
+    hoi4_parser::schema::validate(&serde_json::to_value(game_data)?, &schema).map_err(ParserError::Invalid)?;
+    progress!("Output matches the schema");
+    Ok(())
+}
+
+fn open_output(output_path: &str) -> Result<Box<dyn Write>, ParserError> {
+    Ok(if output_path == "-" {
+        Box::new(BufWriter::new(std::io::stdout().lock()))
+    } else {
+        Box::new(BufWriter::new(File::create(output_path)?))
+    })
+}
+
+// One single-key object per line, in the order metadata, countries, wars,
+// factions, then the remaining top-level sections, e.g.
+// `{"country":{"tag":"GER","data":{...}}}`
+fn write_ndjson(game_data: &GameData, output_path: &str) -> Result<(), ParserError> {
+    let mut writer = open_output(output_path)?;
+    
+    write_record(&mut writer, "metadata", &game_data.metadata)?;
+    for country in &game_data.countries {
+        write_record(&mut writer, "country", country)?;
+    }
+    for war in &game_data.wars {
+        write_record(&mut writer, "war", war)?;
+    }
+    for faction in &game_data.factions {
+        write_record(&mut writer, "faction", faction)?;
+    }
+    write_record(&mut writer, "events", &game_data.events)?;
+    write_record(&mut writer, "capitulations", &game_data.capitulations)?;
+    write_record(&mut writer, "peace_conference", &game_data.peace_conference)?;
+    if let Some(states) = &game_data.states {
+        write_record(&mut writer, "states", states)?;
+    }
+    if let Some(ranking) = &game_data.power_ranking {
+        write_record(&mut writer, "power_ranking", ranking)?;
+    }
     
+    writer.flush()?;
     Ok(())
-}
\ No newline at end of file
+}
+
+// One <TAG>.json per country holding just its data, so the addon can load
+// a single nation without the whole aggregate
+fn write_country_files(game_data: &GameData, dir: &str, compact: bool) -> Result<(), ParserError> {
+    std::fs::create_dir_all(dir)?;
+    for country in &game_data.countries {
+        let path = Path::new(dir).join(format!("{}.json", country.tag));
+        write_json(&country.data, &path.to_string_lossy(), compact)?;
+    }
+    summary!("Wrote {} country files to: {}", game_data.countries.len(), dir);
+    Ok(())
+}
+
+fn write_record<T: Serialize>(writer: &mut dyn Write, kind: &str, value: &T) -> Result<(), ParserError> {
+    serde_json::to_writer(&mut *writer, &HashMap::from([(kind, value)]))?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+// Stream straight into the file rather than building the whole JSON string
+// first; "-" writes to stdout
+fn write_json<T: Serialize>(value: &T, output_path: &str, compact: bool) -> Result<(), ParserError> {
+    let mut writer = open_output(output_path)?;
+    if compact {
+        serde_json::to_writer(&mut writer, value)?;
+    } else {
+        serde_json::to_writer_pretty(&mut writer, value)?;
+    }
+    writeln!(writer)?;
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,46 @@
+// A rough at-a-glance ordering of the great powers. The score is
+//
+//   FACTORY_WEIGHT * (civilian + military factories + dockyards)
+//     + DIVISION_WEIGHT * divisions
+//     + RESEARCH_SLOT_WEIGHT * research slots
+//
+// with anything the save doesn't have counting as zero. It's meant as a
+// quick orientation for the AI, not a faithful strength estimate.
+
+use serde::Serialize;
+
+use crate::enhanced_country::EnhancedCountry;
+
+pub const FACTORY_WEIGHT: f64 = 1.0;
+pub const DIVISION_WEIGHT: f64 = 0.5;
+pub const RESEARCH_SLOT_WEIGHT: f64 = 5.0;
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct PowerRank {
+    pub tag: String,
+    pub score: f64,
+}
+
+pub fn power_score(country: &EnhancedCountry) -> f64 {
+    let factories = country
+        .industry
+        .as_ref()
+        .map(|industry| industry.civilian_factories + industry.military_factories + industry.dockyards)
+        .unwrap_or(0);
+    FACTORY_WEIGHT * f64::from(factories)
+        + DIVISION_WEIGHT * f64::from(country.divisions.unwrap_or(0))
+        + RESEARCH_SLOT_WEIGHT * f64::from(country.research_slots.unwrap_or(0))
+}
+
+// Highest score first, ties broken by tag so the order is stable
+pub fn power_ranking<'a, I>(countries: I) -> Vec<PowerRank>
+where
+    I: IntoIterator<Item = (&'a str, &'a EnhancedCountry)>,
+{
+    let mut ranking: Vec<PowerRank> = countries
+        .into_iter()
+        .map(|(tag, country)| PowerRank { tag: tag.to_string(), score: power_score(country) })
+        .collect();
+    ranking.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.tag.cmp(&b.tag)));
+    ranking
+}
@@ -0,0 +1,397 @@
+// JSON Schema for game_data.json, printed by --schema.
+//
+// Rather than keeping a hand-written schema in sync with the structs, the
+// schema is inferred from two example documents built from the real serde
+// types: one with every optional field filled in (which gives the types)
+// and one with every optional field empty (which shows what can be null).
+// Adding a field to a struct is a compile error here until the examples
+// are updated, so the schema can't silently fall behind.
+//
+// --validate checks output against it with `validate`, which understands
+// just the parts `infer` produces: type, properties and items.
+
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::enhanced_country::{
+    ActiveDecision, Advisor, AiStrategy, Character, CharacterId, CountryLeader, DatabaseCharacter, EnhancedCountry, Expeditionary,
+    Focus, Idea, Industry, Parties, Party, Politics, ResearchSlot, Technology,
+};
+use crate::extraction::{Faction, FiredEvent, StateBuildings, StateInfo, War};
+use crate::ranking::PowerRank;
+use crate::{country_data, CountryData, Events, GameData, Metadata, ParseOptions};
+
+pub fn output_schema() -> Value {
+    let mut schema = infer(&example_output(true), &example_output(false));
+    if let Some(schema) = schema.as_object_mut() {
+        schema.insert("$schema".to_string(), json!("https://json-schema.org/draft/2020-12/schema"));
+        schema.insert("title".to_string(), json!("HOI4 game data"));
+    }
+    // The examples only show the full event list; --events-flat and
+    // --event-counts write the other two shapes
+    if let Some(events) = schema.pointer_mut("/properties/events") {
+        let full = events.take();
+        *events = json!({
+            "anyOf": [
+                full,
+                {"type": "array", "items": {"type": "string"}},
+                {"type": "object"},
+            ]
+        });
+    }
+    // With --localization these ids become `{id, name}` pairs
+    let named = json!({
+        "type": "object",
+        "properties": {"id": {"type": "string"}, "name": {"type": "string"}},
+    });
+    for pointer in LOCALISED_IDS {
+        if let Some(ids) = schema.pointer_mut(&format!("/properties/countries/items/properties/data{}", pointer)) {
+            let plain = ids.take();
+            *ids = json!({"anyOf": [plain, named]});
+        }
+    }
+    schema
+}
+
+// Schema paths under a country's data of the ids localisation names
+const LOCALISED_IDS: &[&str] = &[
+    "/properties/focus/properties/completed/items",
+    "/properties/technology/properties/completed/items",
+    "/properties/conscription_law",
+    "/properties/economy_law",
+    "/properties/trade_law",
+];
+
+fn example_country(populated: bool) -> EnhancedCountry {
+    let some = |value: f64| populated.then_some(value);
+    let party = || Party {
+        popularity: some(25.0),
+        country_leader: populated.then(|| {
+            vec![CountryLeader {
+                ideology: Some("nazism".to_string()),
+                character: Some(Character {
+                    id: Some(1),
+                    r#type: Some(0),
+                    name: Some("GER_adolf_hitler".to_string()),
+                }),
+                traits: vec!["dictator".to_string()],
+            }]
+        }),
+    };
+
+    EnhancedCountry {
+        stability: 0.5,
+        war_support: 0.5,
+        variables: HashMap::new(),
+        politics: populated.then(|| Politics {
+            ruling_party: Some("fascism".to_string()),
+            political_power: Some(100.0),
+            political_power_daily: Some(1.0),
+            parties: Some(Parties {
+                democratic: Some(party()),
+                communism: Some(party()),
+                fascism: Some(party()),
+                neutrality: Some(party()),
+                extras: HashMap::new(),
+            }),
+            ideas: Some(vec![Idea {
+                id: "civilian_economy".to_string(),
+                category: Some("economy".to_string()),
+            }]),
+            last_election: Some("1933.3.5.1".to_string()),
+            elections_allowed: Some(false),
+            advisors: vec![Advisor {
+                slot: Some("political_advisor".to_string()),
+                character_id: populated.then_some(1707),
+                name: populated.then(|| "GER_hjalmar_schacht".to_string()),
+            }],
+        }),
+        focus: populated.then(|| Focus {
+            progress: Some(35.0),
+            current: Some("GER_rhineland".to_string()),
+            current_focuses: vec!["GER_rhineland".to_string()],
+            paused: Some("no".to_string()),
+        }),
+        focus_tree: populated.then(|| "german_focus".to_string()),
+        major: populated.then_some(true),
+        capital: populated.then_some(64),
+        cosmetic_tag: populated.then(|| "GER_german_empire".to_string()),
+        army_experience: some(0.0),
+        navy_experience: some(0.0),
+        air_experience: some(0.0),
+        command_power: some(30.0),
+        research_slots: populated.then_some(3),
+        divisions: populated.then_some(0),
+        army_manpower: populated.then_some(0),
+        ships: populated.then_some(0),
+        planes: populated.then_some(0),
+        resources: HashMap::new(),
+        equipment: HashMap::new(),
+        technology: populated.then(|| Technology {
+            completed: vec!["infantry_weapons".to_string()],
+            in_progress: vec![ResearchSlot {
+                tech: "infantry_weapons1".to_string(),
+                progress: 0.5,
+            }],
+        }),
+        industry: populated.then(Industry::default),
+        manpower: populated.then_some(0),
+        fuel: some(0.0),
+        convoys: populated.then_some(0),
+        air_superiority_regions: vec!["60".to_string()],
+        research_sharing_group: populated.then(|| "commonwealth_research".to_string()),
+        overlord: populated.then(|| "ENG".to_string()),
+        subjects: vec!["CAN".to_string()],
+        conscription_law: populated.then(|| "volunteer_only".to_string()),
+        economy_law: populated.then(|| "civilian_economy".to_string()),
+        trade_law: populated.then(|| "export_focus".to_string()),
+        expeditionary: vec![Expeditionary {
+            to: "SPR".to_string(),
+            divisions: 2,
+        }],
+        at_war: populated,
+        enemies: vec!["ETH".to_string()],
+        decisions: vec![ActiveDecision {
+            id: "GER_mefo_bills_mission".to_string(),
+            days_remaining: some(179.0),
+        }],
+        high_command: vec![Advisor {
+            slot: Some("army_chief".to_string()),
+            character_id: populated.then_some(1685),
+            name: populated.then(|| "GER_ludwig_beck".to_string()),
+        }],
+        ai_strategies: populated.then(|| {
+            vec![AiStrategy {
+                r#type: "10".to_string(),
+                id: populated.then(|| "2".to_string()),
+                target: populated.then(|| "55".to_string()),
+                value: populated.then_some(1),
+            }]
+        }),
+        controlled_foreign_states: populated.then_some(0),
+        occupied_home_states: populated.then_some(0),
+    }
+}
+
+// Built the same way parse_save builds its output, so injected fields are
+// covered too
+fn example_output(populated: bool) -> Value {
+    let country = example_country(populated);
+    let completed = vec!["GER_army_innovations".to_string()];
+    let mut characters = HashMap::new();
+    if populated {
+        characters.insert(1, DatabaseCharacter {
+            id: CharacterId { id: 1, r#type: 0 },
+            token: Some("GER_adolf_hitler".to_string()),
+            template: None,
+            name: Some("GER_adolf_hitler".to_string()),
+            country: Some("GER".to_string()),
+            country_leader: true,
+            traits: vec!["dictator".to_string()],
+            leader_traits: vec!["dictator".to_string()],
+        });
+    }
+    let data = country_data(
+        &country,
+        true,
+        populated.then_some(&completed),
+        &characters,
+        &ParseOptions::default(),
+    );
+
+    let game_data = GameData {
+        metadata: Metadata {
+            player: "GER".to_string(),
+            date: "1936.1.1.12".to_string(),
+            iso_date: "1936-01-01".to_string(),
+            start_date: "1936.1.1.12".to_string(),
+            elapsed_days: 0,
+            parser_version: crate::PARSER_VERSION.to_string(),
+            is_multiplayer: true,
+            human_players: vec!["GER".to_string(), "ITA".to_string()],
+            warnings: vec!["unexpected country tag 'X-1'".to_string()],
+            total_countries: 0,
+            active_countries: 0,
+            completed_focuses: 0,
+            game_rules: populated.then(BTreeMap::new),
+            version: populated.then(|| "Countenance v1.16.9.8bdd (c09b)".to_string()),
+            ironman: populated.then_some(false),
+            checksum: populated.then(|| "ddc1af4cb41f20cf6a43f86f588081c0".to_string()),
+        },
+        events: Events::Full(vec![FiredEvent {
+            name: "news.1".to_string(),
+            tag: populated.then(|| "GER".to_string()),
+            date: populated.then(|| "1936.1.1.12".to_string()),
+            title: None,
+        }]),
+        wars: vec![War {
+            name: populated.then(|| "Spanish Civil War".to_string()),
+            attackers: vec!["SPR".to_string()],
+            defenders: vec!["SPD".to_string()],
+        }],
+        factions: vec![Faction {
+            name: "Axis".to_string(),
+            leader: "GER".to_string(),
+            members: vec!["GER".to_string()],
+        }],
+        capitulations: vec!["ETH".to_string()],
+        peace_conference: vec!["ITA".to_string()],
+        states: populated.then(|| {
+            vec![StateInfo {
+                id: 64,
+                owner: Some("GER".to_string()),
+                controller: Some("GER".to_string()),
+                victory_points: Some(10),
+                buildings: StateBuildings {
+                    infrastructure: 5,
+                    anti_air: 2,
+                    air_base: 6,
+                    ..StateBuildings::default()
+                },
+            }]
+        }),
+        power_ranking: populated.then(|| {
+            vec![PowerRank {
+                tag: "GER".to_string(),
+                score: 100.0,
+            }]
+        }),
+        countries: vec![CountryData {
+            tag: "GER".to_string(),
+            data,
+        }],
+    };
+    serde_json::to_value(game_data).unwrap()
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(number) if number.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+// Build a schema from the populated example, allowing null wherever the
+// sparse example is null (or missing) but the populated one isn't
+fn infer(full: &Value, sparse: &Value) -> Value {
+    let mut schema = Map::new();
+
+    let full_type = type_name(full);
+    if sparse.is_null() && !full.is_null() {
+        schema.insert("type".to_string(), json!([full_type, "null"]));
+    } else {
+        schema.insert("type".to_string(), json!(full_type));
+    }
+
+    match full {
+        Value::Array(items) => {
+            if let Some(first) = items.first() {
+                // With no sparse item to compare against (the list is empty
+                // or null there), any of the item's fields may be null, but
+                // the item itself is still there
+                let item = match sparse.get(0) {
+                    Some(sparse_first) => infer(first, sparse_first),
+                    None => {
+                        let mut item = infer(first, &Value::Null);
+                        item["type"] = json!(type_name(first));
+                        item
+                    }
+                };
+                schema.insert("items".to_string(), item);
+            }
+        }
+        // Empty objects are free-form maps (variables, resources)
+        Value::Object(fields) if !fields.is_empty() => {
+            let properties: Map<String, Value> = fields
+                .iter()
+                .map(|(key, value)| {
+                    let sparse_value = sparse.get(key).unwrap_or(&Value::Null);
+                    (key.clone(), infer(value, sparse_value))
+                })
+                .collect();
+            schema.insert("properties".to_string(), Value::Object(properties));
+        }
+        _ => {}
+    }
+
+    Value::Object(schema)
+}
+
+// Check a document against a schema from `output_schema`, returning the
+// path and reason of the first mismatch. Fields the schema doesn't list
+// are allowed, as are missing ones (several are skipped when empty).
+pub fn validate(value: &Value, schema: &Value) -> Result<(), String> {
+    validate_at(value, schema, "$")
+}
+
+fn validate_at(value: &Value, schema: &Value, path: &str) -> Result<(), String> {
+    if let Some(Value::Array(options)) = schema.get("anyOf") {
+        if options.iter().any(|option| validate_at(value, option, path).is_ok()) {
+            return Ok(());
+        }
+        return Err(format!("{}: doesn't match any of the allowed shapes", path));
+    }
+
+    let allowed: Vec<&str> = match schema.get("type") {
+        Some(Value::String(name)) => vec![name.as_str()],
+        Some(Value::Array(names)) => names.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    let actual = type_name(value);
+    // Whole numbers are fine where any number is
+    let matches = allowed.is_empty()
+        || allowed.contains(&actual)
+        || (actual == "integer" && allowed.contains(&"number"));
+    if !matches {
+        return Err(format!("{}: expected {}, found {}", path, allowed.join(" or "), actual));
+    }
+
+    match value {
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    validate_at(item, item_schema, &format!("{}[{}]", path, index))?;
+                }
+            }
+        }
+        Value::Object(fields) => {
+            if let Some(Value::Object(properties)) = schema.get("properties") {
+                for (key, field) in fields {
+                    if let Some(field_schema) = properties.get(key) {
+                        validate_at(field, field_schema, &format!("{}.{}", path, key))?;
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+// Add every property the schema lists but `value` leaves out, as null, so
+// documents that omit different fields end up with the same keys. Nulls
+// and free-form maps are left alone.
+pub fn fill_nulls(value: &mut Value, schema: &Value) {
+    match value {
+        Value::Object(fields) => {
+            if let Some(Value::Object(properties)) = schema.get("properties") {
+                for (key, field_schema) in properties {
+                    fill_nulls(fields.entry(key.clone()).or_insert(Value::Null), field_schema);
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for item in items {
+                    fill_nulls(item, item_schema);
+                }
+            }
+        }
+        _ => {}
+    }
+}
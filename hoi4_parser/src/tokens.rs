@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::ParserError;
+
+// Load a token definitions file, either the standard `0xXXXX value` text
+// format or the compact binary one (see `parse_binary`). Binary files are
+// recognised by a .bin extension, or by content that can't be text.
+pub fn load_tokens(path: &str) -> Result<HashMap<u16, String>, ParserError> {
+    let token_error = |message: String| ParserError::TokenLoad { path: path.to_string(), message };
+    let data = fs::read(path).map_err(|err| token_error(err.to_string()))?;
+
+    let is_bin = Path::new(path).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("bin"));
+    match std::str::from_utf8(&data) {
+        Ok(content) if !is_bin && !content.contains('\0') => parse_text(content),
+        _ => parse_binary(&data),
+    }
+    .map_err(token_error)
+}
+
+// One `0xXXXX value` pair per line. Blank lines and `#` comments are skipped.
+fn parse_text(content: &str) -> Result<HashMap<u16, String>, String> {
+    let mut tokens = HashMap::new();
+
+    for (line_no, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (id, value) = line
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| format!("line {}: expected '0xXXXX value'", line_no + 1))?;
+
+        let id = u16::from_str_radix(id.trim_start_matches("0x").trim_start_matches("0X"), 16)
+            .map_err(|_| format!("line {}: invalid token id '{}'", line_no + 1, id))?;
+
+        tokens.insert(id, value.trim().to_string());
+    }
+
+    Ok(tokens)
+}
+
+// Back-to-back records of a little-endian u16 id, a u8 name length and
+// that many bytes of UTF-8 name
+fn parse_binary(data: &[u8]) -> Result<HashMap<u16, String>, String> {
+    let mut tokens = HashMap::new();
+    let mut pos = 0;
+
+    while pos < data.len() {
+        let Some(&[lo, hi, len]) = data.get(pos..pos + 3) else {
+            return Err(format!("byte {}: truncated token record", pos));
+        };
+        let name_start = pos + 3;
+        let name = data
+            .get(name_start..name_start + len as usize)
+            .ok_or_else(|| format!("byte {}: token name runs past the end of the file", name_start))?;
+        let name = std::str::from_utf8(name).map_err(|_| format!("byte {}: token name isn't UTF-8", name_start))?;
+
+        tokens.insert(u16::from_le_bytes([lo, hi]), name.to_string());
+        pos = name_start + len as usize;
+    }
+
+    Ok(tokens)
+}
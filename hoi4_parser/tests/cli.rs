@@ -0,0 +1,1438 @@
+// End-to-end runs of the binary over the small saves in tests/fixtures,
+// checking the shape and key values of the JSON it writes
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+use serde_json::Value;
+
+fn fixture(name: &str) -> String {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name).display().to_string()
+}
+
+// Run the parser on a save with the JSON going to stdout
+fn run(save: &str, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_hoi4_parser"))
+        .arg(save)
+        .arg("-")
+        .arg("--quiet")
+        .args(args)
+        .output()
+        .expect("failed to run hoi4_parser")
+}
+
+fn parse(name: &str, args: &[&str]) -> Value {
+    let output = run(&fixture(name), args);
+    assert!(output.status.success(), "hoi4_parser failed: {}", String::from_utf8_lossy(&output.stderr));
+    serde_json::from_slice(&output.stdout).expect("output is not valid JSON")
+}
+
+fn country<'a>(game_data: &'a Value, tag: &str) -> &'a Value {
+    let countries = game_data["countries"].as_array().unwrap();
+    let country = countries.iter().find(|country| country["tag"] == tag);
+    &country.unwrap_or_else(|| panic!("{} missing from output", tag))["data"]
+}
+
+#[test]
+fn minimal_save() {
+    let game_data = parse("minimal.hoi4", &[]);
+
+    assert_eq!(game_data["metadata"]["player"], "GER");
+    assert_eq!(game_data["metadata"]["date"], "1936.1.1.12");
+    assert_eq!(game_data["metadata"]["iso_date"], "1936-01-01");
+    assert_eq!(game_data["metadata"]["total_countries"], 1);
+    assert_eq!(game_data["metadata"]["is_multiplayer"], false);
+    assert_eq!(game_data["metadata"]["human_players"], serde_json::json!(["GER"]));
+
+    let germany = country(&game_data, "GER");
+    assert_eq!(germany["is_player"], true);
+    assert_eq!(germany["focus"]["completed"], serde_json::json!(["GER_rhineland", "GER_army_innovations"]));
+    assert_eq!(germany["stability"], 0.6);
+    assert_eq!(germany["capital"], 64);
+}
+
+#[test]
+fn multi_country_save() {
+    let game_data = parse("multi_country.hoi4", &[]);
+
+    assert_eq!(game_data["metadata"]["player"], "ITA");
+    assert_eq!(game_data["metadata"]["total_countries"], 6);
+    assert_eq!(game_data["metadata"]["active_countries"], 3);
+
+    // The player comes first, and countries without a focus are dropped
+    let countries = game_data["countries"].as_array().unwrap();
+    let tags: Vec<&str> = countries.iter().map(|country| country["tag"].as_str().unwrap()).collect();
+    assert_eq!(tags, ["ITA", "D01", "GER"]);
+
+    let italy = country(&game_data, "ITA");
+    assert_eq!(italy["focus"]["completed"], serde_json::json!(["ITA_italian_empire", "ITA_army_reform"]));
+    assert_eq!(italy["focus"]["current"], "ITA_invade_ethiopia");
+    assert_eq!(italy["politics"]["ruling_party_popularity"], 80.0);
+    assert_eq!(country(&game_data, "GER")["is_player"], false);
+
+    assert_eq!(game_data["wars"][0]["name"], "Italo-Ethiopian War");
+    assert_eq!(game_data["wars"][0]["attackers"], serde_json::json!(["ITA"]));
+    assert_eq!(game_data["factions"][0]["members"], serde_json::json!(["GER", "ITA"]));
+    assert_eq!(game_data["events"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn include_inactive_keeps_every_country() {
+    let game_data = parse("multi_country.hoi4", &["--include-inactive"]);
+    assert_eq!(game_data["countries"].as_array().unwrap().len(), 6);
+
+    // SWE doesn't list stability or war support, so both sit at the default
+    let sweden = country(&game_data, "SWE");
+    assert_eq!(sweden["capital"], Value::Null);
+    assert_eq!(sweden["stability"], 0.5);
+    assert_eq!(sweden["war_support"], 0.5);
+}
+
+#[test]
+fn missing_ideologies_report_zero_popularity() {
+    let game_data = parse("minimal.hoi4", &[]);
+    let popularities = &country(&game_data, "GER")["politics"]["popularities"];
+    assert_eq!(popularities["fascism"], 60.0);
+    assert_eq!(popularities["communism"], 0.0);
+    assert_eq!(popularities["neutrality"], 0.0);
+}
+
+#[test]
+fn country_leader_traits() {
+    let game_data = parse("multi_country.hoi4", &[]);
+    let leader = &country(&game_data, "ITA")["politics"]["parties"]["fascism"]["country_leader"][0];
+
+    // Only the leader role's traits, not the other roles the character holds
+    assert_eq!(leader["traits"], serde_json::json!(["fascist_demagogue", "war_hawk"]));
+    assert_eq!(leader["character"]["name"], "Leader of Italy");
+}
+
+#[test]
+fn quoted_trait_ids_are_unquoted() {
+    // The fixture quotes fascist_demagogue and old_guard
+    let game_data = parse("multi_country.hoi4", &[]);
+    let character = &country(&game_data, "ITA")["politics"]["parties"]["fascism"]["country_leader"][0]["character"];
+    assert_eq!(character["traits"], serde_json::json!(["fascist_demagogue", "war_hawk", "old_guard"]));
+}
+
+#[test]
+fn appointed_advisors() {
+    let game_data = parse("multi_country.hoi4", &[]);
+
+    let advisors = country(&game_data, "ITA")["politics"]["advisors"].as_array().unwrap();
+    assert_eq!(advisors.len(), 2);
+    assert_eq!(advisors[0]["slot"], "political_advisor");
+    assert_eq!(advisors[0]["character_id"], 2);
+    assert_eq!(advisors[0]["name"], "Advisor of Italy");
+
+    assert_eq!(country(&game_data, "GER")["politics"]["advisors"], serde_json::json!([]));
+}
+
+#[test]
+fn command_power_and_high_command() {
+    let game_data = parse("multi_country.hoi4", &[]);
+    let ita = country(&game_data, "ITA");
+
+    assert_eq!(ita["command_power"], 25.5);
+    let high_command = ita["high_command"].as_array().unwrap();
+    assert_eq!(high_command.len(), 1);
+    assert_eq!(high_command[0]["slot"], "army_chief");
+    assert_eq!(high_command[0]["character_id"], 3);
+    assert_eq!(high_command[0]["name"], "Army Chief of Italy");
+
+    let ger = country(&game_data, "GER");
+    assert_eq!(ger["command_power"], serde_json::Value::Null);
+    assert_eq!(ger["high_command"], serde_json::json!([]));
+}
+
+#[test]
+fn fields_keeps_only_the_requested_keys() {
+    let game_data = parse("multi_country.hoi4", &["--fields", "stability,focus,no_such_field"]);
+
+    for country in game_data["countries"].as_array().unwrap() {
+        assert!(country["tag"].is_string());
+        let keys: Vec<&String> = country["data"].as_object().unwrap().keys().collect();
+        assert_eq!(keys, ["focus", "stability"]);
+    }
+}
+
+#[test]
+fn multiplayer_lists_every_human_player() {
+    let game_data = parse("multi_country.hoi4", &[]);
+    assert_eq!(game_data["metadata"]["is_multiplayer"], true);
+    assert_eq!(game_data["metadata"]["human_players"], serde_json::json!(["ITA", "GER"]));
+}
+
+#[test]
+fn war_participants_list_each_other_as_enemies() {
+    let game_data = parse("multi_country.hoi4", &["--include-inactive"]);
+
+    let italy = country(&game_data, "ITA");
+    assert_eq!(italy["at_war"], true);
+    assert_eq!(italy["enemies"], serde_json::json!(["ETH"]));
+
+    let ethiopia = country(&game_data, "ETH");
+    assert_eq!(ethiopia["at_war"], true);
+    assert_eq!(ethiopia["enemies"], serde_json::json!(["ITA"]));
+
+    assert_eq!(country(&game_data, "GER")["at_war"], false);
+    assert_eq!(country(&game_data, "GER")["enemies"], serde_json::json!([]));
+}
+
+#[test]
+fn timeout_aborts_a_runaway_scan() {
+    // Thousands of country blocks that never close: each one's section scan
+    // runs to the end of the save, which takes far longer than the timeout
+    let mut save = String::from("HOI4txt\nplayer=\"AAA\"\ndate=\"1936.1.1.12\"\ncountries={\n");
+    for i in 0..20_000u32 {
+        let tag = [b'A' + (i / 676 % 26) as u8, b'A' + (i / 26 % 26) as u8, b'A' + (i % 26) as u8];
+        save.push_str(&format!("\t{}={{\n\t\tinstances_counter={}\n", std::str::from_utf8(&tag).unwrap(), i));
+    }
+    let options = hoi4_parser::ParseOptions {
+        timeout: Some(std::time::Duration::from_millis(100)),
+        ..Default::default()
+    };
+
+    let started = std::time::Instant::now();
+    let err = hoi4_parser::parse_save_with(save.as_bytes(), &std::collections::HashMap::new(), &options).unwrap_err();
+    assert!(matches!(err, hoi4_parser::ParserError::Timeout(_)), "{:?}", err);
+    // Given up mid-scan, not after it
+    assert!(started.elapsed() < std::time::Duration::from_secs(5), "took {:?}", started.elapsed());
+
+    // The binary reports it and exits non-zero
+    let path = std::env::temp_dir().join(format!("hoi4_parser_runaway_{}.hoi4", std::process::id()));
+    std::fs::write(&path, &save).unwrap();
+    let output = run(&path.display().to_string(), &["--timeout", "0.1"]);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("gave up after"));
+}
+
+#[test]
+fn divisions_lent_abroad_are_expeditionary() {
+    let game_data = parse("multi_country.hoi4", &[]);
+
+    let germany = country(&game_data, "GER");
+    assert_eq!(germany["divisions"], 2);
+    assert_eq!(germany["expeditionary"], serde_json::json!([{"to": "ITA", "divisions": 1}]));
+    assert_eq!(country(&game_data, "ITA")["expeditionary"], serde_json::json!([]));
+}
+
+#[test]
+fn header_only_skips_the_countries() {
+    let output = Command::new(env!("CARGO_BIN_EXE_hoi4_parser"))
+        .args([fixture("multi_country.hoi4").as_str(), "-", "--header-only"])
+        .output()
+        .expect("failed to run hoi4_parser");
+    assert!(output.status.success());
+    let header: Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    assert_eq!(header["header_only"], true);
+    assert_eq!(header["metadata"]["player"], "ITA");
+    assert_eq!(header["metadata"]["date"], "1937.3.15.6");
+    assert_eq!(header["metadata"]["version"], "Countenance v1.16.9.8bdd (c09b)");
+    assert!(header.get("countries").is_none());
+
+    // Progress is left on, so a focus pass would have announced itself
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("Extracting completed focuses"));
+}
+
+#[test]
+fn completed_focuses_survive_without_an_active_focus() {
+    let game_data = parse("minimal.hoi4", &[]);
+    let focus = &country(&game_data, "GER")["focus"];
+
+    assert_eq!(focus["current"], Value::Null);
+    assert_eq!(focus["completed"], serde_json::json!(["GER_rhineland", "GER_army_innovations"]));
+}
+
+#[test]
+fn completed_focus_counts() {
+    let game_data = parse("multi_country.hoi4", &[]);
+    assert_eq!(country(&game_data, "ITA")["completed_focus_count"], 2);
+    assert_eq!(country(&game_data, "GER")["completed_focus_count"], 1);
+    assert_eq!(game_data["metadata"]["completed_focuses"], 4);
+
+    // Only the countries written out count towards the total
+    let game_data = parse("multi_country.hoi4", &["--only", "GER"]);
+    assert_eq!(game_data["metadata"]["completed_focuses"], 1);
+}
+
+#[test]
+fn dynamic_tags_are_extracted() {
+    let game_data = parse("multi_country.hoi4", &["--only", "D01"]);
+    let countries = game_data["countries"].as_array().unwrap();
+    assert_eq!(countries.len(), 1);
+
+    let splinter = country(&game_data, "D01");
+    assert_eq!(splinter["focus"]["completed"], serde_json::json!(["generic_workers_militia"]));
+    assert!(game_data["metadata"]["warnings"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn output_dir_is_created_and_written_to() {
+    let dir = std::env::temp_dir().join(format!("hoi4_parser_output_dir_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let nested = dir.join("data");
+
+    let status = Command::new(env!("CARGO_BIN_EXE_hoi4_parser"))
+        .arg(fixture("minimal.hoi4"))
+        .arg("--output-dir")
+        .arg(&nested)
+        .arg("--quiet")
+        .status()
+        .expect("failed to run hoi4_parser");
+    assert!(status.success());
+
+    let written = std::fs::read(nested.join("game_data.json")).expect("game_data.json wasn't written");
+    let game_data: Value = serde_json::from_slice(&written).unwrap();
+    assert_eq!(game_data["metadata"]["player"], "GER");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn tech_sharing_group_membership() {
+    let game_data = parse("multi_country.hoi4", &["--include-inactive"]);
+    assert_eq!(country(&game_data, "GER")["research_sharing_group"], "axis_research");
+    assert_eq!(country(&game_data, "ITA")["research_sharing_group"], "axis_research");
+    assert_eq!(country(&game_data, "ETH")["research_sharing_group"], Value::Null);
+}
+
+#[test]
+fn equipment_stockpiles() {
+    let game_data = parse("multi_country.hoi4", &[]);
+    let expected = serde_json::json!({"infantry_equipment": 750.5, "artillery_equipment": 40.0});
+    assert_eq!(country(&game_data, "GER")["equipment"], expected);
+
+    let game_data = parse("multi_country.hoi4", &["--equipment-detail"]);
+    let expected = serde_json::json!({
+        "infantry_equipment_0": 500.0,
+        "infantry_equipment_1": 250.5,
+        "artillery_equipment_1": 40.0,
+    });
+    assert_eq!(country(&game_data, "GER")["equipment"], expected);
+}
+
+#[test]
+fn stability_drift_within_epsilon_is_inactive() {
+    // NOR has a focus running but sits at 0.5000001 stability
+    let game_data = parse("multi_country.hoi4", &["--only", "NOR"]);
+    assert!(game_data["countries"].as_array().unwrap().is_empty());
+
+    let game_data = parse("multi_country.hoi4", &["--only", "NOR", "--activity-epsilon", "0"]);
+    assert_eq!(game_data["countries"][0]["tag"], "NOR");
+}
+
+#[test]
+fn state_building_levels() {
+    let game_data = parse("multi_country.hoi4", &["--include-states"]);
+    let buildings = &game_data["states"][0]["buildings"];
+    assert_eq!(buildings["infrastructure"], 3);
+    assert_eq!(buildings["land_forts"], 2);
+    assert_eq!(buildings["air_base"], 0);
+}
+
+#[test]
+fn state_owners_and_controllers() {
+    let game_data = parse("multi_country.hoi4", &["--include-states"]);
+    let states = game_data["states"].as_array().unwrap();
+    // The unowned sea state is left out
+    let ids: Vec<u64> = states.iter().map(|state| state["id"].as_u64().unwrap()).collect();
+    assert_eq!(ids, [64, 65, 270]);
+
+    assert_eq!(states[0]["owner"], "GER");
+    assert_eq!(states[0]["controller"], "GER");
+    assert!(states[0]["victory_points"].is_null());
+    assert_eq!(states[2]["owner"], "ETH");
+    assert_eq!(states[2]["controller"], "ITA");
+    assert_eq!(states[2]["victory_points"], 5);
+}
+
+#[test]
+fn validate_accepts_localised_output() {
+    // Focuses, technologies and laws become {id, name} pairs
+    for save in ["minimal.hoi4", "multi_country.hoi4"] {
+        let output = run(&fixture(save), &["--validate", "--localization", &fixture("localisation/english")]);
+        assert_eq!(output.status.code(), Some(0), "{}: {}", save, String::from_utf8_lossy(&output.stderr));
+    }
+}
+
+#[test]
+fn validate_accepts_real_output_and_rejects_bad_values() {
+    for args in [&["--validate"][..], &["--validate", "--event-counts", "--include-states"]] {
+        parse("multi_country.hoi4", args);
+    }
+
+    let schema = hoi4_parser::schema::output_schema();
+    let mut game_data = parse("multi_country.hoi4", &[]);
+    assert!(hoi4_parser::schema::validate(&game_data, &schema).is_ok());
+
+    game_data["countries"][0]["data"]["stability"] = Value::String("high".to_string());
+    let err = hoi4_parser::schema::validate(&game_data, &schema).unwrap_err();
+    assert!(err.contains("countries[0].data.stability"), "{}", err);
+}
+
+#[test]
+fn game_rules_in_metadata() {
+    let game_data = parse("minimal.hoi4", &[]);
+    let expected = serde_json::json!({
+        "allow_puppeting": "yes",
+        "historical_ai_focuses": "ALLOW_HISTORICAL_FOCUSES_NO",
+        "difficulty": "hard",
+    });
+    assert_eq!(game_data["metadata"]["game_rules"], expected);
+
+    // No game_rules block, so no key
+    let game_data = parse("multi_country.hoi4", &[]);
+    assert!(game_data["metadata"].get("game_rules").is_none());
+}
+
+#[test]
+fn text_and_binary_tokens_load_the_same() {
+    let text = hoi4_parser::tokens::load_tokens(&fixture("tokens.txt")).unwrap();
+    let binary = hoi4_parser::tokens::load_tokens(&fixture("tokens.bin")).unwrap();
+    assert_eq!(text.len(), 3);
+    assert_eq!(text[&0x2b5c], "historical_ai_focuses");
+    assert_eq!(text, binary);
+}
+
+#[test]
+fn laws_from_ideas() {
+    let game_data = parse("minimal.hoi4", &[]);
+    let ger = country(&game_data, "GER");
+    assert_eq!(ger["conscription_law"], "extensive_conscription");
+    assert_eq!(ger["economy_law"], "war_economy");
+    assert_eq!(ger["trade_law"], Value::Null);
+}
+
+// Golden files in tests/snapshots lock in the full output for each fixture,
+// so a renamed or reshaped field shows up as a test failure. After an
+// intentional change, rerun with UPDATE_SNAPSHOTS=1 to rewrite them and
+// review the diff.
+fn assert_snapshot(name: &str) {
+    let game_data = parse(&format!("{}.hoi4", name), &[]);
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots").join(format!("{}.json", name));
+
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, serde_json::to_string_pretty(&game_data).unwrap() + "\n").unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("can't read {} ({}); run with UPDATE_SNAPSHOTS=1 to create it", path.display(), err));
+    let expected: Value = serde_json::from_str(&expected).expect("snapshot is not valid JSON");
+    assert!(
+        game_data == expected,
+        "output for {} no longer matches {}; rerun with UPDATE_SNAPSHOTS=1 if the change is intended",
+        name,
+        path.display()
+    );
+}
+
+#[test]
+fn minimal_snapshot() {
+    assert_snapshot("minimal");
+}
+
+#[test]
+fn multi_country_snapshot() {
+    assert_snapshot("multi_country");
+}
+
+#[test]
+fn puppet_relationships_both_ways() {
+    let game_data = parse("multi_country.hoi4", &["--include-inactive"]);
+    assert_eq!(country(&game_data, "GER")["subjects"], serde_json::json!(["NOR"]));
+    assert_eq!(country(&game_data, "GER")["overlord"], Value::Null);
+    assert_eq!(country(&game_data, "NOR")["overlord"], "GER");
+    assert_eq!(country(&game_data, "NOR")["subjects"], serde_json::json!([]));
+}
+
+#[test]
+fn events_since_drops_older_events() {
+    let game_data = parse("minimal.hoi4", &[]);
+    assert_eq!(game_data["events"].as_array().unwrap().len(), 2);
+
+    // germany.1 fired 12 days before the save, germany.2 214 days before
+    let game_data = parse("minimal.hoi4", &["--events-since", "30"]);
+    let events = game_data["events"].as_array().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["name"], "germany.1");
+}
+
+#[test]
+fn ideology_color_follows_ruling_party() {
+    let game_data = parse("minimal.hoi4", &[]);
+    assert_eq!(country(&game_data, "GER")["ideology_color"], "#964b00");
+
+    // SWE has no politics block, so no ruling party
+    let game_data = parse("multi_country.hoi4", &["--include-inactive"]);
+    assert!(country(&game_data, "SWE").get("ideology_color").is_none());
+}
+
+#[test]
+fn include_null_fields_adds_missing_keys() {
+    let game_data = parse("minimal.hoi4", &[]);
+    let ger = country(&game_data, "GER").as_object().unwrap();
+    assert!(!ger.contains_key("cosmetic_tag"));
+    assert!(!ger.contains_key("idle_research_slots"));
+
+    let game_data = parse("minimal.hoi4", &["--include-null-fields"]);
+    let ger_with_nulls = country(&game_data, "GER").as_object().unwrap();
+    assert_eq!(ger_with_nulls["cosmetic_tag"], Value::Null);
+    assert_eq!(ger_with_nulls["idle_research_slots"], Value::Null);
+    assert!(ger_with_nulls["focus"].as_object().unwrap().contains_key("current"));
+    // Nothing that was there before changes
+    for (key, value) in ger {
+        assert_eq!(&ger_with_nulls[key], value, "{} changed", key);
+    }
+}
+
+#[test]
+fn ai_strategies_only_with_flag() {
+    let game_data = parse("multi_country.hoi4", &[]);
+    assert!(country(&game_data, "ITA").get("ai_strategies").is_none());
+
+    let game_data = parse("multi_country.hoi4", &["--ai-strategy"]);
+    let expected = serde_json::json!([{"type": "10", "id": "2", "target": "55", "value": 150}]);
+    assert_eq!(country(&game_data, "ITA")["ai_strategies"], expected);
+    assert_eq!(country(&game_data, "GER")["ai_strategies"], serde_json::json!([]));
+}
+
+#[test]
+fn focus_current_in_any_form() {
+    let game_data = parse("multi_country.hoi4", &["--include-inactive"]);
+    // Quoted, unquoted and as a reference block
+    assert_eq!(country(&game_data, "GER")["focus"]["current"], "GER_four_year_plan");
+    assert_eq!(country(&game_data, "NOR")["focus"]["current"], "NOR_neutrality");
+    assert_eq!(country(&game_data, "D01")["focus"]["current"], "generic_collectivist_rhetoric");
+}
+
+#[test]
+fn split_countries_writes_one_file_per_country() {
+    let dir = std::env::temp_dir().join(format!("hoi4_parser_split_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let output = run(&fixture("multi_country.hoi4"), &["--split-countries", dir.to_str().unwrap()]);
+    assert!(output.status.success(), "hoi4_parser failed: {}", String::from_utf8_lossy(&output.stderr));
+    let game_data: Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    let mut files: Vec<String> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    files.sort();
+    assert_eq!(files, ["D01.json", "GER.json", "ITA.json"]);
+
+    let italy: Value = serde_json::from_slice(&std::fs::read(dir.join("ITA.json")).unwrap()).unwrap();
+    assert_eq!(&italy, country(&game_data, "ITA"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn start_date_and_elapsed_days() {
+    // 1936.1.1 to 1937.3.15 is a full year plus 31 + 28 + 14 days
+    let game_data = parse("multi_country.hoi4", &[]);
+    assert_eq!(game_data["metadata"]["start_date"], "1936.1.1.12");
+    assert_eq!(game_data["metadata"]["elapsed_days"], 365 + 73);
+
+    // No start_date, so the vanilla one is assumed and noted
+    let game_data = parse("minimal.hoi4", &[]);
+    assert_eq!(game_data["metadata"]["start_date"], "1936.1.1.12");
+    assert_eq!(game_data["metadata"]["elapsed_days"], 0);
+    let warnings = game_data["metadata"]["warnings"].as_array().unwrap();
+    assert!(warnings.iter().any(|warning| warning.as_str().unwrap().contains("start_date")));
+}
+
+#[test]
+fn truncated_save_warns_and_keeps_earlier_countries() {
+    // Cut off partway through ITA, after GER's section closed
+    let game_data = parse("truncated.hoi4", &[]);
+
+    assert!(game_data.get("partial").is_none());
+    let warnings = game_data["metadata"]["warnings"].as_array().unwrap();
+    assert!(warnings.iter().any(|warning| warning.as_str().unwrap().contains("section for ITA")));
+    assert_eq!(game_data["metadata"]["total_countries"], 1);
+
+    // GER comes through in full, not just the regex-recovered parts
+    let germany = country(&game_data, "GER");
+    assert_eq!(germany["focus"]["completed"], serde_json::json!(["GER_rhineland"]));
+    assert_eq!(germany["focus"]["current"], "GER_four_year_plan");
+    assert_eq!(germany["politics"]["political_power"], 120.0);
+    assert_eq!(germany["divisions"], 2);
+    assert_eq!(game_data["events"].as_array().unwrap().len(), 2);
+
+    // And batch mode writes it like any other save
+    let dir = std::env::temp_dir().join(format!("hoi4_parser_truncated_batch_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::copy(fixture("truncated.hoi4"), dir.join("truncated.hoi4")).unwrap();
+    let status = Command::new(env!("CARGO_BIN_EXE_hoi4_parser"))
+        .arg("--batch")
+        .arg(&dir)
+        .arg("--output")
+        .arg(&dir)
+        .arg("--quiet")
+        .status()
+        .expect("failed to run hoi4_parser");
+    assert!(status.success());
+    let batch: Value = serde_json::from_slice(&std::fs::read(dir.join("game_data_truncated.json")).unwrap()).unwrap();
+    assert_eq!(batch, game_data);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn malformed_country_gives_partial_output() {
+    // ITA's stability isn't a number, so the full parse fails
+    let output = run(&fixture("malformed.hoi4"), &[]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("failed to parse save"));
+
+    let game_data: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(game_data["partial"], true);
+    assert_eq!(game_data["metadata"]["player"], "GER");
+    assert_eq!(game_data["metadata"]["date"], "1936.1.1.12");
+    assert_eq!(game_data["completed_focuses"]["GER"], serde_json::json!(["GER_rhineland"]));
+    assert_eq!(game_data["characters"]["GER"], serde_json::json!(["Leader of Germany"]));
+}
+
+#[test]
+fn simultaneous_focuses() {
+    let game_data = parse("multi_country.hoi4", &[]);
+    let focus = &country(&game_data, "GER")["focus"];
+    assert_eq!(focus["current_focuses"], serde_json::json!(["GER_four_year_plan", "GER_rearmament"]));
+    assert_eq!(focus["current"], "GER_four_year_plan");
+}
+
+#[test]
+fn event_noise_is_filtered() {
+    let names = |game_data: &Value| -> Vec<String> {
+        game_data["events"].as_array().unwrap().iter().map(|event| event.as_str().unwrap().to_string()).collect()
+    };
+
+    // Numbers, single characters and "=" always go
+    let game_data = parse("noisy_events.hoi4", &["--events-flat"]);
+    assert_eq!(names(&game_data), ["debug_marker", "germany.3", "news.1"]);
+
+    let game_data = parse("noisy_events.hoi4", &["--events-flat", "--event-ignore", "debug_marker"]);
+    assert_eq!(names(&game_data), ["germany.3", "news.1"]);
+}
+
+#[test]
+fn binary_save_decodes_with_tokens() {
+    let game_data = parse("binary.hoi4", &["--tokens", &fixture("binary_tokens.txt")]);
+    assert_eq!(game_data["metadata"]["player"], "GER");
+    assert_eq!(game_data["metadata"]["date"], "1936.1.1.12");
+
+    // Without the tokens there's nothing to resolve the field names with
+    let output = run(&fixture("binary.hoi4"), &[]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--tokens"));
+}
+
+#[test]
+fn division_counts_and_army_manpower() {
+    let game_data = parse("military.hoi4", &[]);
+    let germany = country(&game_data, "GER");
+    assert_eq!(germany["divisions"], 3);
+    assert_eq!(germany["army_manpower"], 25500);
+}
+
+#[test]
+fn ships_and_planes() {
+    let game_data = parse("military.hoi4", &[]);
+    let germany = country(&game_data, "GER");
+    assert_eq!(germany["ships"], 2);
+    // Four air wings over two pools
+    assert_eq!(germany["planes"], 184);
+    // Ships are in the units block but aren't divisions
+    assert_eq!(germany["divisions"], 3);
+}
+
+#[test]
+fn compact_output_is_a_single_line() {
+    let compact = run(&fixture("minimal.hoi4"), &["--compact"]);
+    let pretty = run(&fixture("minimal.hoi4"), &[]);
+    assert!(compact.status.success() && pretty.status.success());
+
+    let compact_text = String::from_utf8(compact.stdout).unwrap();
+    let pretty_text = String::from_utf8(pretty.stdout).unwrap();
+    assert_eq!(compact_text.trim_end().lines().count(), 1);
+    assert!(pretty_text.trim_end().lines().count() > 1);
+    assert_eq!(
+        serde_json::from_str::<Value>(&compact_text).unwrap(),
+        serde_json::from_str::<Value>(&pretty_text).unwrap()
+    );
+}
+
+#[test]
+fn resource_stockpiles() {
+    let game_data = parse("multi_country.hoi4", &[]);
+    // Produced less exported
+    assert_eq!(country(&game_data, "GER")["resources"], serde_json::json!({"oil": 4.0, "steel": 24.0}));
+    assert_eq!(country(&game_data, "ITA")["resources"], serde_json::json!({}));
+}
+
+#[test]
+fn active_war_with_both_sides() {
+    let game_data = parse("multi_country.hoi4", &[]);
+    let expected = serde_json::json!([{
+        "name": "Italo-Ethiopian War",
+        "attackers": ["ITA"],
+        "defenders": ["ETH"],
+    }]);
+    assert_eq!(game_data["wars"], expected);
+}
+
+#[test]
+fn faction_leaders_and_members() {
+    let game_data = parse("multi_country.hoi4", &[]);
+    let factions = game_data["factions"].as_array().unwrap();
+    assert_eq!(factions.len(), 2);
+    assert_eq!(factions[0]["leader"], "GER");
+
+    // A leader missing from its own member list is added, and repeats dropped
+    assert_eq!(factions[1]["name"], "Nordic Council");
+    assert_eq!(factions[1]["leader"], "SWE");
+    assert_eq!(factions[1]["members"], serde_json::json!(["SWE", "NOR", "D01"]));
+}
+
+#[test]
+fn completed_and_in_progress_research() {
+    let game_data = parse("multi_country.hoi4", &[]);
+    let expected = serde_json::json!({
+        "completed": ["infantry_weapons"],
+        "in_progress": [{"tech": "support_weapons", "progress": 0.35}],
+    });
+    assert_eq!(country(&game_data, "ITA")["technology"], expected);
+
+    // Nothing being researched still gives an empty list
+    assert_eq!(country(&game_data, "GER")["technology"]["in_progress"], serde_json::json!([]));
+}
+
+#[test]
+fn army_navy_and_air_experience() {
+    let game_data = parse("military.hoi4", &[]);
+    let germany = country(&game_data, "GER");
+    assert_eq!(germany["army_experience"], 12.5);
+    assert_eq!(germany["navy_experience"], 3.0);
+    assert_eq!(germany["air_experience"], 7.25);
+
+    let game_data = parse("minimal.hoi4", &[]);
+    assert_eq!(country(&game_data, "GER")["army_experience"], Value::Null);
+}
+
+#[test]
+fn country_model_covers_both_former_structs() {
+    let game_data = parse("multi_country.hoi4", &[]);
+    let germany = country(&game_data, "GER");
+    assert_eq!(germany["focus"]["current"], "GER_four_year_plan");
+    assert_eq!(germany["focus"]["focus_tree"], "german_focus");
+    assert_eq!(germany["politics"]["political_power"], 120.0);
+    assert_eq!(germany["army_experience"], 5.0);
+    assert_eq!(country(&game_data, "ITA")["cosmetic_tag"], "ROM");
+}
+
+#[test]
+fn crlf_save_matches_lf() {
+    let lf = std::fs::read_to_string(fixture("multi_country.hoi4")).unwrap();
+    let crlf_path = std::env::temp_dir().join(format!("hoi4_parser_crlf_{}.hoi4", std::process::id()));
+    std::fs::write(&crlf_path, lf.replace('\n', "\r\n")).unwrap();
+
+    let output = run(&crlf_path.display().to_string(), &[]);
+    std::fs::remove_file(&crlf_path).unwrap();
+    assert!(output.status.success(), "hoi4_parser failed: {}", String::from_utf8_lossy(&output.stderr));
+    let crlf: Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    let lf = parse("multi_country.hoi4", &[]);
+    assert_eq!(country(&crlf, "ITA")["focus"]["completed"], serde_json::json!(["ITA_italian_empire", "ITA_army_reform"]));
+    assert_eq!(crlf, lf);
+}
+
+#[test]
+fn escaped_quotes_in_focus_ids() {
+    let game_data = parse("quoting.hoi4", &[]);
+    let completed = &country(&game_data, "GER")["focus"]["completed"];
+    assert_eq!(completed, &serde_json::json!(["GER_the_\"new\"_order", "GER_rhineland"]));
+}
+
+#[test]
+fn braces_inside_quoted_strings() {
+    // GER's name holds unbalanced braces; ITA's section must still be found
+    let game_data = parse("quoting.hoi4", &["--include-inactive"]);
+    assert_eq!(game_data["metadata"]["total_countries"], 2);
+    assert_eq!(country(&game_data, "GER")["focus"]["completed"].as_array().unwrap().len(), 2);
+    assert_eq!(country(&game_data, "ITA")["focus"]["completed"], serde_json::json!(["ITA_italian_empire"]));
+    assert_eq!(country(&game_data, "ITA")["focus"]["current"], "ITA_army_reform");
+}
+
+#[test]
+fn missing_save_exits_non_zero() {
+    let output = run(&fixture("no_such_save.hoi4"), &[]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no_such_save.hoi4"));
+}
+
+#[test]
+fn only_keeps_the_requested_tags() {
+    let game_data = parse("multi_country.hoi4", &["--only", "GER,ITA"]);
+    let tags: Vec<&str> = game_data["countries"].as_array().unwrap().iter().map(|c| c["tag"].as_str().unwrap()).collect();
+    assert_eq!(tags, ["ITA", "GER"]);
+
+    // Still subject to the active filter, which SWE (at the defaults) fails
+    let game_data = parse("multi_country.hoi4", &["--countries", "SWE"]);
+    assert!(game_data["countries"].as_array().unwrap().is_empty());
+
+    let output = run(&fixture("multi_country.hoi4"), &["--only", "germany"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("'germany' is not a valid country tag"));
+}
+
+#[test]
+fn inactive_countries_need_a_flag_or_enough_political_power() {
+    let tags = |game_data: &Value| -> Vec<String> {
+        game_data["countries"].as_array().unwrap().iter().map(|c| c["tag"].as_str().unwrap().to_string()).collect()
+    };
+
+    // SWE sits at the defaults; ETH has moved but runs no focus
+    let game_data = parse("multi_country.hoi4", &[]);
+    assert_eq!(tags(&game_data), ["ITA", "D01", "GER"]);
+
+    let game_data = parse("multi_country.hoi4", &["--min-political-power", "10"]);
+    assert_eq!(tags(&game_data), ["ITA", "D01", "ETH", "GER"]);
+}
+
+#[test]
+fn paused_focus_keeps_its_current_id() {
+    let game_data = parse("multi_country.hoi4", &[]);
+    let paused = &country(&game_data, "D01")["focus"];
+    assert_eq!(paused["paused"], "yes");
+    assert_eq!(paused["is_paused"], true);
+    assert_eq!(paused["current"], "generic_collectivist_rhetoric");
+
+    let running = &country(&game_data, "ITA")["focus"];
+    assert_eq!(running["paused"], "no");
+    assert_eq!(running["is_paused"], false);
+}
+
+#[test]
+fn focus_days_remaining() {
+    // ITA is 20 days into its focus
+    let game_data = parse("multi_country.hoi4", &[]);
+    assert_eq!(country(&game_data, "ITA")["focus"]["days_remaining"], 50.0);
+
+    let game_data = parse("multi_country.hoi4", &["--focus-days", "35"]);
+    assert_eq!(country(&game_data, "ITA")["focus"]["days_remaining"], 15.0);
+
+    // Overrunning a short focus bottoms out at 0
+    let game_data = parse("multi_country.hoi4", &["--focus-days", "10"]);
+    assert_eq!(country(&game_data, "ITA")["focus"]["days_remaining"], 0.0);
+
+    assert_eq!(run(&fixture("multi_country.hoi4"), &["--focus-days", "0"]).status.code(), Some(2));
+}
+
+#[test]
+fn ideas_are_structured_entries() {
+    let game_data = parse("minimal.hoi4", &[]);
+    let ideas = &country(&game_data, "GER")["politics"]["ideas"];
+    // Laws get their slot; national spirits have no category in the save
+    assert_eq!(ideas[0], serde_json::json!({"id": "sour_loser", "category": null}));
+    assert_eq!(ideas[1], serde_json::json!({"id": "extensive_conscription", "category": "mobilization_laws"}));
+}
+
+#[test]
+fn factories_summed_over_owned_states() {
+    let game_data = parse("multi_country.hoi4", &[]);
+    let expected = serde_json::json!({"civilian_factories": 3, "military_factories": 1, "dockyards": 1});
+    assert_eq!(country(&game_data, "GER")["industry"], expected);
+    assert_eq!(country(&game_data, "ITA")["industry"], Value::Null);
+}
+
+#[test]
+fn save_read_from_stdin() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_hoi4_parser"))
+        .args(["-", "-", "--quiet"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to run hoi4_parser");
+    child.stdin.take().unwrap().write_all(&std::fs::read(fixture("minimal.hoi4")).unwrap()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    let game_data: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(game_data, parse("minimal.hoi4", &[]));
+}
+
+#[test]
+fn stdout_output_holds_only_the_json() {
+    // Progress left on, so it has to go somewhere other than stdout
+    let output = Command::new(env!("CARGO_BIN_EXE_hoi4_parser"))
+        .args([fixture("minimal.hoi4").as_str(), "-"])
+        .output()
+        .expect("failed to run hoi4_parser");
+    assert!(output.status.success());
+
+    let game_data: Value = serde_json::from_slice(&output.stdout).expect("stdout is not just JSON");
+    assert_eq!(game_data["metadata"]["player"], "GER");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Extracting completed focuses"));
+}
+
+#[test]
+fn quiet_silences_progress_but_keeps_the_summary() {
+    let output = run(&fixture("minimal.hoi4"), &[]);
+    assert!(output.status.success());
+    serde_json::from_slice::<Value>(&output.stdout).expect("stdout is not just JSON");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("Extracting"), "{}", stderr);
+    assert!(stderr.contains("Data extracted to: -"), "{}", stderr);
+}
+
+#[test]
+fn schema_is_valid_json() {
+    let output = Command::new(env!("CARGO_BIN_EXE_hoi4_parser")).arg("--schema").output().expect("failed to run hoi4_parser");
+    assert!(output.status.success());
+    let schema: Value = serde_json::from_slice(&output.stdout).expect("--schema printed invalid JSON");
+
+    for key in ["metadata", "events", "countries"] {
+        assert!(schema["properties"].get(key).is_some(), "schema has no {}", key);
+    }
+    let country = &schema["properties"]["countries"]["items"]["properties"]["data"]["properties"];
+    assert!(country["focus"].is_object() && country["politics"].is_object());
+}
+
+#[test]
+fn events_are_deduplicated_and_sorted() {
+    // The save lists news.1 twice, and ahead of germany.3
+    let game_data = parse("noisy_events.hoi4", &[]);
+    let names: Vec<&str> = game_data["events"].as_array().unwrap().iter().map(|e| e["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["debug_marker", "germany.3", "news.1"]);
+
+    let game_data = parse("noisy_events.hoi4", &["--event-counts"]);
+    assert_eq!(game_data["events"], serde_json::json!({"debug_marker": 1, "germany.3": 1, "news.1": 2}));
+}
+
+#[test]
+fn events_carry_their_country_and_date() {
+    let game_data = parse("minimal.hoi4", &[]);
+    let expected = serde_json::json!({"name": "germany.1", "tag": "GER", "date": "1935.12.20.12"});
+    assert_eq!(game_data["events"][0], expected);
+
+    // Bare id= entries have neither
+    let game_data = parse("multi_country.hoi4", &[]);
+    assert_eq!(game_data["events"][0], serde_json::json!({"name": "italy.3", "tag": null, "date": null}));
+}
+
+#[test]
+fn version_and_checksum_in_metadata() {
+    let game_data = parse("minimal.hoi4", &[]);
+    assert_eq!(game_data["metadata"]["version"], "Countenance v1.16.9.8bdd (c09b)");
+    assert_eq!(game_data["metadata"]["checksum"], "5d1b2c4e8f3a6b7c9d0e1f2a3b4c5d6e");
+    // Not in the save, so left out
+    assert!(game_data["metadata"].get("ironman").is_none());
+
+    let game_data = parse("multi_country.hoi4", &[]);
+    assert!(game_data["metadata"].get("checksum").is_none());
+}
+
+#[test]
+fn manpower_fuel_and_convoys() {
+    let game_data = parse("military.hoi4", &[]);
+    let germany = country(&game_data, "GER");
+    assert_eq!(germany["manpower"], 250000);
+    assert_eq!(germany["fuel"], 1500.0);
+    assert_eq!(germany["convoys"], 40);
+
+    let game_data = parse("minimal.hoi4", &[]);
+    assert_eq!(country(&game_data, "GER")["fuel"], Value::Null);
+}
+
+#[test]
+fn capitulated_countries() {
+    let game_data = parse("multi_country.hoi4", &[]);
+    assert_eq!(game_data["capitulations"], serde_json::json!(["ETH"]));
+    assert_eq!(game_data["peace_conference"], serde_json::json!([]));
+}
+
+#[test]
+fn parse_save_as_a_library() {
+    let bytes = std::fs::read(fixture("multi_country.hoi4")).unwrap();
+    let game_data = hoi4_parser::parse_save(&bytes, &std::collections::HashMap::new()).unwrap();
+
+    assert_eq!(game_data.metadata.player, "ITA");
+    assert_eq!(game_data.countries[0].tag, "ITA");
+    assert_eq!(game_data.wars.len(), 1);
+    // Same structure the binary writes out
+    assert_eq!(serde_json::to_value(&game_data).unwrap(), parse("multi_country.hoi4", &[]));
+}
+
+#[test]
+fn error_variants() {
+    use hoi4_parser::ParserError;
+    let no_tokens = std::collections::HashMap::new();
+    let parse_bytes = |bytes: &[u8]| hoi4_parser::parse_save(bytes, &no_tokens).unwrap_err();
+
+    let err = parse_bytes(&std::fs::read(fixture("malformed.hoi4")).unwrap());
+    assert!(matches!(err, ParserError::Partial { .. }), "{:?}", err);
+    assert!(matches!(parse_bytes(b"not a save"), ParserError::Decode(_)));
+    assert!(matches!(parse_bytes(&std::fs::read(fixture("binary.hoi4")).unwrap()), ParserError::MissingTokens));
+
+    let err = hoi4_parser::tokens::load_tokens(&fixture("no_such_tokens.txt")).unwrap_err();
+    assert!(matches!(err, ParserError::TokenLoad { .. }), "{:?}", err);
+
+    // The binary checks the path itself before reading
+    let output = run(&fixture("no_such_save.hoi4"), &[]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains(&ParserError::SaveNotFound(fixture("no_such_save.hoi4")).to_string()));
+}
+
+#[test]
+fn parallel_extraction_matches_a_serial_pass() {
+    // Synthetic 80-country save, each country with its own number of focuses
+    let tags: Vec<String> = (0..80u8).map(|i| String::from_utf8(vec![b'A' + i / 26, b'A' + i % 26, b'X']).unwrap()).collect();
+    let mut save = String::from("HOI4txt\nplayer=\"AAX\"\ndate=\"1936.1.1.12\"\ncountries={\n");
+    for (i, tag) in tags.iter().enumerate() {
+        save.push_str(&format!("\t{}={{\n\t\tinstances_counter=1\n\t\tstability=0.600\n\t\twar_support=0.400\n\t\tfocus={{\n", tag));
+        for focus in 0..=i % 5 {
+            save.push_str(&format!("\t\t\tcompleted=\"{}_focus_{}\"\n", tag, focus));
+        }
+        save.push_str(&format!("\t\t\tcurrent=\"{}_next\"\n\t\t\tprogress=10.000\n\t\t}}\n\t}}\n", tag));
+    }
+    save.push_str("}\n");
+
+    let sections = hoi4_parser::extraction::find_country_sections(&save, None).unwrap().complete;
+    assert_eq!(sections.len(), 80);
+    let count_focuses = |_: &str, section: &str| Some(section.matches("completed=").count());
+    let parallel = hoi4_parser::extraction::map_countries(&sections, count_focuses);
+    let serial: std::collections::BTreeMap<String, usize> =
+        sections.iter().map(|(tag, section)| (tag.clone(), count_focuses(tag, section).unwrap())).collect();
+    assert_eq!(parallel, serial);
+
+    let game_data = hoi4_parser::parse_save(save.as_bytes(), &std::collections::HashMap::new()).unwrap();
+    assert_eq!(game_data.countries.len(), 80);
+    for (i, tag) in tags.iter().enumerate() {
+        let country = game_data.countries.iter().find(|country| &country.tag == tag).unwrap();
+        let expected: Vec<String> = (0..=i % 5).map(|focus| format!("{}_focus_{}", tag, focus)).collect();
+        assert_eq!(country.data["focus"]["completed"], serde_json::json!(expected), "{}", tag);
+    }
+}
+
+#[test]
+fn repeated_library_parses_reuse_the_regexes() {
+    // One process, many saves: the compiled regexes are shared across calls
+    let no_tokens = std::collections::HashMap::new();
+    let saves: Vec<Vec<u8>> = ["minimal.hoi4", "multi_country.hoi4", "military.hoi4", "quoting.hoi4"]
+        .iter()
+        .map(|name| std::fs::read(fixture(name)).unwrap())
+        .collect();
+    let first: Vec<Value> =
+        saves.iter().map(|bytes| serde_json::to_value(hoi4_parser::parse_save(bytes, &no_tokens).unwrap()).unwrap()).collect();
+
+    for _ in 0..5 {
+        for (bytes, expected) in saves.iter().zip(&first) {
+            let game_data = hoi4_parser::parse_save(bytes, &no_tokens).unwrap();
+            assert_eq!(&serde_json::to_value(&game_data).unwrap(), expected);
+        }
+    }
+    let multi_country = &first[1];
+    assert_eq!(multi_country["wars"][0]["name"], "Italo-Ethiopian War");
+    assert_eq!(multi_country["factions"][1]["name"], "Nordic Council");
+}
+
+#[test]
+fn batch_writes_one_file_per_save() {
+    let dir = std::env::temp_dir().join(format!("hoi4_parser_batch_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let saves = dir.join("saves");
+    let out = dir.join("out");
+    std::fs::create_dir_all(&saves).unwrap();
+    std::fs::copy(fixture("minimal.hoi4"), saves.join("minimal.hoi4")).unwrap();
+    std::fs::copy(fixture("multi_country.hoi4"), saves.join("multi_country.hoi4")).unwrap();
+    std::fs::write(saves.join("notes.txt"), "not a save").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_hoi4_parser"))
+        .arg("--batch")
+        .arg(&saves)
+        .arg("--output")
+        .arg(&out)
+        .arg("--quiet")
+        .output()
+        .expect("failed to run hoi4_parser");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let mut written: Vec<String> =
+        std::fs::read_dir(&out).unwrap().map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned()).collect();
+    written.sort();
+    assert_eq!(written, ["game_data_minimal.json", "game_data_multi_country.json"]);
+    let game_data: Value = serde_json::from_slice(&std::fs::read(out.join("game_data_multi_country.json")).unwrap()).unwrap();
+    assert_eq!(game_data["metadata"]["player"], "ITA");
+
+    // A broken save is reported and skipped, and the run exits non-zero
+    std::fs::write(saves.join("broken.hoi4"), "not a save").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_hoi4_parser"))
+        .arg("--batch")
+        .arg(&saves)
+        .arg("--output")
+        .arg(&out)
+        .arg("--quiet")
+        .output()
+        .expect("failed to run hoi4_parser");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Parsed 2 of 3 saves"));
+    assert!(!out.join("game_data_broken.json").exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn diff_reports_a_new_focus_and_a_vanished_party() {
+    // The later save: GER finished one more focus and its neutrality party is gone
+    let old_save = std::fs::read_to_string(fixture("multi_country.hoi4")).unwrap();
+    let new_save = old_save
+        .replacen("\t\t\tcompleted=\"GER_rhineland\"\n", "\t\t\tcompleted=\"GER_rhineland\"\n\t\t\tcompleted=\"GER_four_year_plan\"\n", 1)
+        .replacen("\t\t\t\tneutrality={\n\t\t\t\t\tpopularity=30.000\n\t\t\t\t}\n", "", 1)
+        .replacen("date=\"1937.3.15.6\"", "date=\"1937.6.1.6\"", 1);
+    let new_path = std::env::temp_dir().join(format!("hoi4_parser_diff_{}.hoi4", std::process::id()));
+    std::fs::write(&new_path, new_save).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_hoi4_parser"))
+        .arg("--diff")
+        .arg(fixture("multi_country.hoi4"))
+        .arg(&new_path)
+        .arg("--quiet")
+        .output()
+        .expect("failed to run hoi4_parser");
+    std::fs::remove_file(&new_path).unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let diff: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(diff["to_date"], "1937.6.1.6");
+    assert_eq!(diff["focuses_completed"], serde_json::json!({"GER": ["GER_four_year_plan"]}));
+    assert_eq!(
+        diff["ideology_shifts"],
+        serde_json::json!([{"tag": "GER", "ideology": "neutrality", "from": 30.0, "to": 0.0}])
+    );
+}
+
+#[test]
+fn modded_ideologies_are_kept() {
+    let game_data = parse("politics.hoi4", &[]);
+    let parties = &country(&game_data, "GER")["politics"]["parties"];
+    assert_eq!(parties["fascism"]["popularity"], 60.0);
+    assert_eq!(parties["monarchism"]["popularity"], 10.0);
+    assert_eq!(parties.as_object().unwrap().len(), 5);
+
+    // Flattened in the output, but held apart from the vanilla four
+    let parties: hoi4_parser::enhanced_country::Parties = serde_json::from_value(parties.clone()).unwrap();
+    assert_eq!(parties.extras.keys().collect::<Vec<_>>(), ["monarchism"]);
+    assert_eq!(parties.fascism.unwrap().popularity, Some(60.0));
+}
+
+#[test]
+fn political_power_daily_when_the_save_has_it() {
+    let game_data = parse("politics.hoi4", &[]);
+    assert_eq!(country(&game_data, "GER")["politics"]["political_power_daily"], 1.25);
+
+    // Vanilla saves don't store it
+    let game_data = parse("multi_country.hoi4", &[]);
+    assert!(country(&game_data, "GER")["politics"]["political_power_daily"].is_null());
+}
+
+#[test]
+fn occupation_counts_from_the_state_list() {
+    // ITA holds ETH's state 270
+    let game_data = parse("multi_country.hoi4", &["--include-states", "--include-inactive"]);
+    assert_eq!(country(&game_data, "ITA")["controlled_foreign_states"], 1);
+    assert_eq!(country(&game_data, "ITA")["occupied_home_states"], 0);
+    assert_eq!(country(&game_data, "ETH")["controlled_foreign_states"], 0);
+    assert_eq!(country(&game_data, "ETH")["occupied_home_states"], 1);
+    assert_eq!(country(&game_data, "GER")["occupied_home_states"], 0);
+
+    // Left out without the state list
+    let game_data = parse("multi_country.hoi4", &[]);
+    assert!(country(&game_data, "ITA").get("controlled_foreign_states").is_none());
+}
+
+#[test]
+fn timed_decisions() {
+    let game_data = parse("politics.hoi4", &[]);
+    assert_eq!(
+        country(&game_data, "GER")["decisions"],
+        serde_json::json!([{"id": "GER_war_propaganda", "days_remaining": 12.5}])
+    );
+
+    let game_data = parse("politics.hoi4", &["--all-decisions"]);
+    let ids: Vec<&str> =
+        country(&game_data, "GER")["decisions"].as_array().unwrap().iter().map(|d| d["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, ["GER_war_propaganda", "GER_hire_advisors"]);
+}
+
+#[test]
+fn max_countries_keeps_the_most_political_power() {
+    let game_data = parse("four_countries.hoi4", &[]);
+    assert_eq!(game_data["countries"].as_array().unwrap().len(), 4);
+
+    // ENG has 200 PP; ITA and SOV tie at 150 and ITA wins on the tag
+    let game_data = parse("four_countries.hoi4", &["--max-countries", "2"]);
+    let tags: Vec<&str> = game_data["countries"].as_array().unwrap().iter().map(|c| c["tag"].as_str().unwrap()).collect();
+    assert_eq!(tags, ["ENG", "ITA"]);
+    assert_eq!(game_data["metadata"]["active_countries"], 2);
+}
+
+#[test]
+fn low_activity_player_comes_first() {
+    // SWE would sort last by tag, and sits at the default stats
+    let game_data = parse("quiet_player.hoi4", &[]);
+    let tags: Vec<&str> = game_data["countries"].as_array().unwrap().iter().map(|c| c["tag"].as_str().unwrap()).collect();
+    assert_eq!(tags, ["SWE", "GER", "ITA"]);
+}
+
+#[test]
+fn inactive_player_is_kept_and_flagged() {
+    let game_data = parse("quiet_player.hoi4", &[]);
+    assert_eq!(game_data["metadata"]["active_countries"], 3);
+    assert_eq!(country(&game_data, "SWE")["is_player"], true);
+    assert_eq!(country(&game_data, "SWE")["stability"], 0.5);
+    assert_eq!(country(&game_data, "GER")["is_player"], false);
+
+    // Other countries at the same stats are still filtered out
+    let save = std::fs::read_to_string(fixture("quiet_player.hoi4")).unwrap().replace("player=\"SWE\"", "player=\"ITA\"");
+    let game_data = serde_json::to_value(hoi4_parser::parse_save(save.as_bytes(), &std::collections::HashMap::new()).unwrap()).unwrap();
+    let tags: Vec<&str> = game_data["countries"].as_array().unwrap().iter().map(|c| c["tag"].as_str().unwrap()).collect();
+    assert_eq!(tags, ["ITA", "GER"]);
+}
+
+#[test]
+fn focus_names_from_localisation() {
+    let game_data = parse("minimal.hoi4", &["--localization", &fixture("localisation/english")]);
+    // Keys missing from the files keep the id as their name
+    assert_eq!(
+        country(&game_data, "GER")["focus"]["completed"],
+        serde_json::json!([
+            {"id": "GER_rhineland", "name": "Rhineland"},
+            {"id": "GER_army_innovations", "name": "GER_army_innovations"}
+        ])
+    );
+}
+
+#[test]
+fn ideas_and_events_share_the_localisation() {
+    let game_data = parse("minimal.hoi4", &["--localization", &fixture("localisation/english")]);
+    // Colour codes are dropped and \n escapes become newlines
+    let ideas = &country(&game_data, "GER")["politics"]["ideas"];
+    assert_eq!(ideas[0]["name"], "Sour Loser");
+    assert_eq!(ideas[1]["name"], "extensive_conscription");
+    assert_eq!(game_data["events"][0]["title"], "A New\nChancellor");
+    assert!(game_data["events"][1].get("title").is_none_or(Value::is_null));
+
+    let localizer = hoi4_parser::localization::Localizer::load(&fixture("localisation/english")).unwrap();
+    assert_eq!(localizer.lookup("GER_rhineland"), Some("Rhineland"));
+    assert_eq!(localizer.event_title("germany.1"), Some("A New\nChancellor"));
+}
+
+#[test]
+fn parser_version_in_metadata() {
+    let game_data = parse("minimal.hoi4", &[]);
+    assert_eq!(game_data["metadata"]["parser_version"], env!("CARGO_PKG_VERSION"));
+}
+
+#[test]
+fn odd_country_tags_are_warned_about_but_kept() {
+    // A1B and D01 have the shape of dynamic tags; germany doesn't look like a tag at all
+    let game_data = parse("odd_tags.hoi4", &["--include-inactive"]);
+    let warnings = game_data["metadata"]["warnings"].as_array().unwrap();
+    let tag_warnings: Vec<&str> =
+        warnings.iter().filter_map(Value::as_str).filter(|warning| warning.contains("country tag")).collect();
+    assert_eq!(tag_warnings, ["unexpected country tag 'germany'"]);
+    assert_eq!(game_data["countries"].as_array().unwrap().len(), 4);
+
+    // --only takes the same tags the warning accepts
+    let game_data = parse("odd_tags.hoi4", &["--include-inactive", "--only", "A1B,D01"]);
+    assert_eq!(game_data["countries"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn repeated_runs_are_byte_identical() {
+    let first = run(&fixture("multi_country.hoi4"), &["--include-inactive"]);
+    let second = run(&fixture("multi_country.hoi4"), &["--include-inactive"]);
+    assert!(first.status.success());
+    assert_eq!(first.stdout, second.stdout);
+
+    // The player, then the rest by tag
+    let game_data: Value = serde_json::from_slice(&first.stdout).unwrap();
+    let tags: Vec<&str> = game_data["countries"].as_array().unwrap().iter().map(|c| c["tag"].as_str().unwrap()).collect();
+    let mut rest = tags[1..].to_vec();
+    rest.sort();
+    assert_eq!(tags[0], "ITA");
+    assert_eq!(tags[1..], rest[..]);
+}
+
+#[test]
+fn research_slots_and_idle_slots() {
+    // ITA has three slots and one technology in progress
+    let game_data = parse("multi_country.hoi4", &[]);
+    let italy = country(&game_data, "ITA");
+    assert_eq!(italy["research_slots"], 3);
+    assert_eq!(italy["idle_research_slots"], 2);
+
+    // Countries the save has no slot count for have neither
+    let germany = country(&game_data, "GER");
+    assert!(germany.get("research_slots").is_none_or(Value::is_null));
+    assert!(germany.get("idle_research_slots").is_none_or(Value::is_null));
+}
+
+#[test]
+fn focus_tree_in_the_focus_json() {
+    let game_data = parse("multi_country.hoi4", &[]);
+    assert_eq!(country(&game_data, "GER")["focus"]["focus_tree"], "german_focus");
+    // ITA's save block doesn't name its tree
+    assert_eq!(country(&game_data, "ITA")["focus"]["focus_tree"], Value::Null);
+}
+
+#[test]
+fn cosmetic_tag_for_renamed_nations() {
+    let game_data = parse("multi_country.hoi4", &["--include-inactive"]);
+    assert_eq!(country(&game_data, "ITA")["cosmetic_tag"], "ROM");
+    // An empty cosmetic_tag means the nation is back to its own name
+    assert!(country(&game_data, "SWE").get("cosmetic_tag").is_none());
+    assert!(country(&game_data, "GER").get("cosmetic_tag").is_none());
+}
+
+#[test]
+fn more_factories_rank_higher() {
+    let game_data = parse("industry.hoi4", &["--power-ranking", "--include-inactive"]);
+    assert_eq!(
+        game_data["power_ranking"],
+        serde_json::json!([{"tag": "GER", "score": 5.0}, {"tag": "ITA", "score": 2.0}])
+    );
+
+    let game_data = parse("industry.hoi4", &["--include-inactive"]);
+    assert!(game_data.get("power_ranking").is_none_or(Value::is_null));
+}
+
+#[test]
+fn windows_1252_save_keeps_accented_names() {
+    let bytes = std::fs::read(fixture("windows_1252.hoi4")).unwrap();
+    assert!(std::str::from_utf8(&bytes).is_err());
+
+    let game_data = parse("windows_1252.hoi4", &[]);
+    let leader = &country(&game_data, "ARG")["politics"]["parties"]["neutrality"]["country_leader"][0];
+    assert_eq!(leader["character"]["name"], "Juan Perón");
+    assert_eq!(country(&game_data, "ARG")["focus"]["completed"], serde_json::json!(["ARG_la_década_infame"]));
+}
+
+#[test]
+fn stats_report_the_focus_total() {
+    // GER has two completed focuses and ITA one
+    let output = run(&fixture("quoting.hoi4"), &["--stats", "--include-inactive"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("  Completed focuses: 3\n"), "{}", stderr);
+    assert!(stderr.contains("  Wars: 0\n"));
+
+    let output = run(&fixture("quoting.hoi4"), &["--include-inactive"]);
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("Stats:"));
+}
+
+#[test]
+fn gzipped_save_matches_plain() {
+    // Two gzip members, split halfway through the save
+    let gzipped = run(&fixture("multi_country.hoi4.gz"), &[]);
+    let plain = run(&fixture("multi_country.hoi4"), &[]);
+    assert!(gzipped.status.success(), "{}", String::from_utf8_lossy(&gzipped.stderr));
+    assert_eq!(gzipped.stdout, plain.stdout);
+
+    // "abc", then a member whose only content is a back-reference into the first
+    let cross_member: [u8; 44] = [
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0x4b, 0x4c, 0x4a, 0x06, 0x00, 0xc2, 0x41, 0x24,
+        0x35, 0x03, 0x00, 0x00, 0x00, 0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0x03, 0x22, 0x00,
+        0xc2, 0x41, 0x24, 0x35, 0x03, 0x00, 0x00, 0x00,
+    ];
+    let err = hoi4_parser::gzip::decompress(&cross_member).unwrap_err();
+    assert!(err.to_string().contains("distance too far back"), "{}", err);
+}
+
+#[test]
+fn air_superiority_regions() {
+    // GER out-flies ITA over region 60; over 61 they're level, so neither holds it
+    let game_data = parse("military.hoi4", &[]);
+    assert_eq!(country(&game_data, "GER")["air_superiority_regions"], serde_json::json!(["60"]));
+
+    let game_data = parse("minimal.hoi4", &[]);
+    assert_eq!(country(&game_data, "GER")["air_superiority_regions"], serde_json::json!([]));
+}
+
+#[test]
+fn ndjson_has_one_record_per_line() {
+    let output = run(&fixture("multi_country.hoi4"), &["--ndjson"]);
+    assert!(output.status.success());
+    let records: Vec<Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("line is not valid JSON"))
+        .collect();
+
+    // Metadata, 3 countries, 1 war and 2 factions, then events,
+    // capitulations and peace_conference
+    assert_eq!(records.len(), 1 + 3 + 1 + 2 + 3);
+    assert_eq!(records[0]["metadata"]["player"], "ITA");
+    let count = |key: &str| records.iter().filter(|record| record.get(key).is_some()).count();
+    assert_eq!(count("country"), 3);
+    assert_eq!(count("war"), 1);
+    assert_eq!(count("faction"), 2);
+    assert_eq!(records[1]["country"]["tag"], "ITA");
+}
+
+#[test]
+fn missing_stability_defaults_to_half() {
+    // Neither country lists stability; DEN doesn't list war support either,
+    // so it's at the game defaults and its running focus alone isn't enough
+    let save = "HOI4txt\nplayer=\"GER\"\ndate=\"1936.1.1.12\"\ncountries={\n\
+        \tITA={\n\t\tinstances_counter=1\n\t\twar_support=0.700\n\t\tfocus={\n\t\t\tcurrent=\"ITA_italian_empire\"\n\t\t\tprogress=3.000\n\t\t}\n\t}\n\
+        \tDEN={\n\t\tinstances_counter=2\n\t\tfocus={\n\t\t\tcurrent=\"DEN_neutrality\"\n\t\t\tprogress=3.000\n\t\t}\n\t}\n}\n";
+    let game_data = hoi4_parser::parse_save(save.as_bytes(), &std::collections::HashMap::new()).unwrap();
+    let game_data = serde_json::to_value(game_data).unwrap();
+
+    let italy = country(&game_data, "ITA");
+    assert_eq!(italy["stability"], 0.5);
+    assert_eq!(italy["war_support"], 0.7);
+    assert!(game_data["countries"].as_array().unwrap().iter().all(|c| c["tag"] != "DEN"));
+}
+
+#[test]
+fn capital_round_trips() {
+    use hoi4_parser::enhanced_country::EnhancedCountry;
+
+    let game_data = parse("minimal.hoi4", &[]);
+    assert_eq!(country(&game_data, "GER")["capital"], 64);
+
+    let germany: EnhancedCountry = serde_json::from_value(serde_json::json!({"capital": 64})).unwrap();
+    assert_eq!(germany.capital, Some(64));
+    let json = serde_json::to_value(&germany).unwrap();
+    assert_eq!(json["capital"], 64);
+    let again: EnhancedCountry = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(again.capital, Some(64));
+    assert_eq!(serde_json::to_value(&again).unwrap(), json);
+}
+
+#[test]
+fn ruling_party_popularity() {
+    let game_data = parse("politics.hoi4", &[]);
+    let politics = &country(&game_data, "GER")["politics"];
+    assert_eq!(politics["ruling_party"], "fascism");
+    assert_eq!(politics["ruling_party_popularity"], 60.0);
+
+    // A ruling party with no entry under parties has no popularity to report
+    let save = std::fs::read_to_string(fixture("politics.hoi4")).unwrap().replace("ruling_party=fascism", "ruling_party=anarchism");
+    let game_data = serde_json::to_value(hoi4_parser::parse_save(save.as_bytes(), &std::collections::HashMap::new()).unwrap()).unwrap();
+    let politics = &country(&game_data, "GER")["politics"];
+    assert_eq!(politics["ruling_party"], "anarchism");
+    assert!(politics.get("ruling_party_popularity").is_none_or(Value::is_null));
+}